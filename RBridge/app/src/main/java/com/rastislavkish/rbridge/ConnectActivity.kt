@@ -20,8 +20,12 @@ import androidx.appcompat.app.AppCompatActivity
 import android.content.Intent
 import android.os.Bundle
 import android.view.View
+import android.widget.AdapterView
+import android.widget.ArrayAdapter
 import android.widget.Button
+import android.widget.CheckBox
 import android.widget.EditText
+import android.widget.ListView
 
 import kotlinx.serialization.*
 import kotlinx.serialization.json.Json
@@ -30,7 +34,14 @@ class ConnectActivity : AppCompatActivity() {
 
     private lateinit var addressEditText: EditText
     private lateinit var portEditText: EditText
+    private lateinit var passwordEditText: EditText
+    private lateinit var tlsCheckBox: CheckBox
+    private lateinit var fingerprintEditText: EditText
     private lateinit var connectButton: Button
+    private lateinit var findServersButton: Button
+    private lateinit var serversListView: ListView
+
+    private lateinit var serversAdapter: ArrayAdapter<DiscoveredServer>
 
     override fun onCreate(savedInstanceState: Bundle?) {
         super.onCreate(savedInstanceState)
@@ -38,8 +49,55 @@ class ConnectActivity : AppCompatActivity() {
 
         addressEditText=findViewById(R.id.addressEditText)
         portEditText=findViewById(R.id.portEditText)
+        passwordEditText=findViewById(R.id.passwordEditText)
+        tlsCheckBox=findViewById(R.id.tlsCheckBox)
+        fingerprintEditText=findViewById(R.id.fingerprintEditText)
         connectButton=findViewById(R.id.connectButton)
         connectButton.setOnClickListener(this::connectButtonClickHandler)
+        findServersButton=findViewById(R.id.findServersButton)
+        findServersButton.setOnClickListener(this::findServersButtonClickHandler)
+
+        serversAdapter=ArrayAdapter(this, android.R.layout.simple_list_item_1, mutableListOf<DiscoveredServer>())
+        serversListView=findViewById(R.id.serversListView)
+        serversListView.adapter=serversAdapter
+        serversListView.onItemClickListener=AdapterView.OnItemClickListener { _, _, position, _ -> serverClickHandler(position) }
+
+        findServers()
+        }
+
+    fun findServersButtonClickHandler(view: View) {
+        findServers()
+        }
+
+    private fun findServers() {
+        findServersButton.isEnabled=false
+        findServersButton.text="Searching"
+
+        Thread {
+            val servers=try {
+                ServerDiscovery.discover()
+                }
+            catch (e: Exception) {
+                listOf<DiscoveredServer>()
+                }
+
+            runOnUiThread {
+                serversAdapter.clear()
+                serversAdapter.addAll(servers)
+
+                findServersButton.isEnabled=true
+                findServersButton.text="Find servers"
+                findServersButton.announceForAccessibility(if (servers.isEmpty()) "No servers found" else "Found ${servers.size} servers")
+                }
+            }.start()
+        }
+    private fun serverClickHandler(position: Int) {
+        val server=serversAdapter.getItem(position) ?: return
+
+        addressEditText.setText(server.address)
+        portEditText.setText(server.port.toString())
+        tlsCheckBox.isChecked=server.tls
+        passwordEditText.requestFocus()
         }
 
     fun connectButtonClickHandler(view: View) {
@@ -48,8 +106,11 @@ class ConnectActivity : AppCompatActivity() {
 
         val address: String=addressEditText.text.toString()
         val port: Int=portEditText.text.toString().toInt()
+        val password: String=passwordEditText.text.toString()
+        val tls: Boolean=tlsCheckBox.isChecked
+        val fingerprint: String=fingerprintEditText.text.toString()
 
-        val result=ConnectActivityResult(address, port)
+        val result=ConnectActivityResult(address, port, password, tls, fingerprint)
 
         val resultIntent=Intent()
         resultIntent.putExtra("result", Json.encodeToString(result))
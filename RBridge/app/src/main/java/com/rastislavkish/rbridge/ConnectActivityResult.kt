@@ -25,6 +25,9 @@ import kotlinx.serialization.json.Json
 class ConnectActivityResult(
     val address: String,
     val port: Int,
+    val password: String,
+    val tls: Boolean=false,
+    val fingerprint: String="",
     ) {
 
     companion object {
@@ -32,6 +32,8 @@ import kotlinx.serialization.json.Json
 import com.rastislavkish.rtk.Speech
 
 import com.rastislavkish.gdt.Gesture
+import com.rastislavkish.gdt.Swipe
+import com.rastislavkish.gdt.SwipeSequence
 import com.rastislavkish.gdt.TouchWrapper
 
 class MainActivity : AppCompatActivity() {
@@ -43,6 +45,12 @@ class MainActivity : AppCompatActivity() {
     private lateinit var speech: Speech
     private lateinit var touchWrapper: TouchWrapper
 
+    //While the server is in trackpad mode, a single finger moves the pointer
+    private var trackpadMode=false
+    private var trackpadPointerId: Int?=null
+    private var trackpadX=0f
+    private var trackpadY=0f
+
     override fun onCreate(savedInstanceState: Bundle?) {
         super.onCreate(savedInstanceState)
         setContentView(R.layout.activity_main)
@@ -60,24 +68,70 @@ class MainActivity : AppCompatActivity() {
             touchWrapper.registerGestureListener(this::touchWrapperGestureHandler)
             }
 
+        if (trackpadMode)
+        trackpadTouchHandler(event)
+
         touchWrapper.onTouchEvent(event)
 
         return true
         }
 
+    //Streams the movement of a lone finger, fractions of pixels are kept for the next event so slow movements aren't lost
+    //Putting down another finger stops streaming until all fingers are lifted, so multi-finger gestures like switching the trackpad off still work
+    private fun trackpadTouchHandler(event: MotionEvent) {
+        when (event.actionMasked) {
+            MotionEvent.ACTION_DOWN -> {
+                trackpadPointerId=event.getPointerId(0)
+                trackpadX=event.getX(0)
+                trackpadY=event.getY(0)
+                }
+            MotionEvent.ACTION_POINTER_DOWN, MotionEvent.ACTION_UP, MotionEvent.ACTION_CANCEL -> trackpadPointerId=null
+            MotionEvent.ACTION_MOVE -> {
+                val pointerIndex=event.findPointerIndex(trackpadPointerId ?: return)
+                if (pointerIndex<0)
+                return
+
+                val deltaX=(event.getX(pointerIndex)-trackpadX).toInt()
+                val deltaY=(event.getY(pointerIndex)-trackpadY).toInt()
+
+                if (deltaX!=0 || deltaY!=0) {
+                    rBridgeClient.sendPointerMotion(deltaX, deltaY)
+
+                    trackpadX+=deltaX
+                    trackpadY+=deltaY
+                    }
+                }
+            }
+        }
+
     private fun touchWrapperGestureHandler(gesture: Gesture) {
+        //One-finger swipes are pointer movement in trackpad mode
+        if (trackpadMode && ((gesture is Swipe && gesture.fingerCount==1) || (gesture is SwipeSequence && gesture.fingerCount==1)))
+        return
+
         rBridgeClient.sendGesture(gesture)
         }
     private fun rBridgeClientMessageHandler(message: String) {
         speech.speak(message)
         }
+    private fun rBridgeClientAuthenticationResultHandler(success: Boolean) {
+        speech.speak(if (success) "Connected" else "Authentication failed")
+        }
+    private fun rBridgeClientTrackpadModeHandler(trackpadMode: Boolean) {
+        runOnUiThread {
+            this.trackpadMode=trackpadMode
+            trackpadPointerId=null
+            }
+        }
 
     private fun connectActivityResult(result: ActivityResult) {
         if (result.resultCode==RESULT_OK) {
             val connectActivityResult=ConnectActivityResult.fromIntent(result.data, "result", "MainActivity")
 
-            rBridgeClient=RBridgeClient(connectActivityResult.address, connectActivityResult.port)
+            rBridgeClient=RBridgeClient(connectActivityResult.address, connectActivityResult.port, connectActivityResult.password, connectActivityResult.tls, connectActivityResult.fingerprint)
             rBridgeClient.setOnMessageListener(this::rBridgeClientMessageHandler)
+            rBridgeClient.setOnAuthenticationResultListener(this::rBridgeClientAuthenticationResultHandler)
+            rBridgeClient.setOnTrackpadModeListener(this::rBridgeClientTrackpadModeHandler)
             rBridgeClient.connect()
             }
         else {
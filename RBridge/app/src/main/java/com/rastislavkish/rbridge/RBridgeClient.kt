@@ -18,6 +18,16 @@ package com.rastislavkish.rbridge
 
 import java.net.URI
 import java.nio.ByteBuffer
+import java.security.MessageDigest
+import java.security.cert.CertificateException
+import java.security.cert.X509Certificate
+
+import javax.crypto.Mac
+import javax.crypto.spec.SecretKeySpec
+import javax.net.ssl.SSLContext
+import javax.net.ssl.SSLParameters
+import javax.net.ssl.TrustManager
+import javax.net.ssl.X509TrustManager
 
 import kotlinx.serialization.*
 import kotlinx.serialization.json.Json
@@ -34,9 +44,24 @@ import com.rastislavkish.gdt.SwipeDirection
 class RBridgeClient(
     val address: String,
     val port: Int,
-    ) : WebSocketClient(URI("ws://$address:$port")) {
+    val password: String,
+    val tls: Boolean=false,
+    val fingerprint: String="",
+    ) : WebSocketClient(URI(if (tls) "wss://$address:$port" else "ws://$address:$port")) {
 
     private var onMessageListener: ((String) -> Unit)?=null
+    private var onAuthenticationResultListener: ((Boolean) -> Unit)?=null
+    private var onTrackpadModeListener: ((Boolean) -> Unit)?=null
+
+    init {
+        //Self-signed certificates can't be verified against the system's authorities, the fingerprint the server prints on start is trusted instead
+        if (tls && fingerprint.isNotBlank()) {
+            val sslContext=SSLContext.getInstance("TLS")
+            sslContext.init(null, arrayOf<TrustManager>(PinnedCertificateTrustManager(fingerprint)), null)
+
+            setSocketFactory(sslContext.socketFactory)
+            }
+        }
 
     fun sendGesture(gesture: Gesture) {
         if (gesture is Swipe) {
@@ -88,13 +113,42 @@ class RBridgeClient(
             }
         }
 
+    //Sends finger movement in pixels while the server is in trackpad mode, as big-endian 16-bit deltas
+    fun sendPointerMotion(x: Int, y: Int) {
+        if (!isOpen)
+        return
+
+        val buffer=ByteBuffer.allocate(5)
+
+        buffer.put(b(5)) //ClientMessage::PointerMotion
+        buffer.putShort(x.coerceIn(Short.MIN_VALUE.toInt(), Short.MAX_VALUE.toInt()).toShort())
+        buffer.putShort(y.coerceIn(Short.MIN_VALUE.toInt(), Short.MAX_VALUE.toInt()).toShort())
+
+        buffer.rewind()
+
+        send(buffer)
+        }
+
     fun setOnMessageListener(listener: (String) -> Unit) {
         onMessageListener=listener
         }
+    fun setOnAuthenticationResultListener(listener: (Boolean) -> Unit) {
+        onAuthenticationResultListener=listener
+        }
+    fun setOnTrackpadModeListener(listener: (Boolean) -> Unit) {
+        onTrackpadModeListener=listener
+        }
+
+    override fun onSetSSLParameters(sslParameters: SSLParameters) {
+        //A pinned certificate identifies the server on its own, and it rarely names the address it's reached on
+        //Endpoint identification is unavailable before API 24
+        if (fingerprint.isBlank() && android.os.Build.VERSION.SDK_INT>=24)
+        super.onSetSSLParameters(sslParameters)
+        }
 
     override fun onOpen(handshakeData: ServerHandshake) {
         android.util.Log.d("RBridge", "Connection established")
-        send("random_password")
+        sendHello()
         }
 
     override fun onClose(code: Int, reason: String, remote: Boolean) {
@@ -106,7 +160,42 @@ class RBridgeClient(
         }
 
     override fun onMessage(message: ByteBuffer) {
+        if (message.remaining()<2)
+        return
+
+        val version=message.get().toInt()
+        if (version!=PROTOCOL_VERSION) {
+            android.util.Log.d("RBridge", "Received a message of unsupported protocol version $version")
+            return
+            }
 
+        when (message.get().toInt()) {
+            0 -> { //ServerMessage::AuthenticationChallenge
+                val challenge=ByteArray(message.remaining())
+                message.get(challenge)
+
+                sendAuthenticationResponse(challenge)
+                }
+            1 -> { //ServerMessage::AuthenticationResult
+                if (message.hasRemaining())
+                onAuthenticationResultListener?.invoke(message.get().toInt()!=0)
+                }
+            4 -> onMessageListener?.invoke("Settings rejected: ${readString(message)}") //ServerMessage::SettingsRejected
+            5 -> onMessageListener?.invoke(readString(message)) //ServerMessage::ActionSelected
+            6 -> onMessageListener?.invoke(readString(message)) //ServerMessage::SchemeChanged
+            8 -> onMessageListener?.invoke("Error: ${readString(message)}") //ServerMessage::Error
+            9 -> android.util.Log.d("RBridge", "Server accepted protocol version ${message.get()}") //ServerMessage::Hello
+            10 -> onMessageListener?.invoke("Incompatible server: ${readString(message)}") //ServerMessage::IncompatibleClient
+            11 -> onMessageListener?.invoke(readString(message)) //ServerMessage::ProcessOutput
+            12 -> { //ServerMessage::TrackpadMode
+                if (message.hasRemaining()) {
+                    val trackpadMode=message.get().toInt()!=0
+
+                    onTrackpadModeListener?.invoke(trackpadMode)
+                    onMessageListener?.invoke(if (trackpadMode) "Trackpad on" else "Trackpad off")
+                    }
+                }
+            }
         }
 
     override fun onError(ex: Exception) {
@@ -167,6 +256,39 @@ class RBridgeClient(
             );
         }
 
+    private fun sendHello() {
+        val buffer=ByteBuffer.allocate(6)
+
+        buffer.put(b(4)) //ClientMessage::Hello
+        buffer.put(b(PROTOCOL_VERSION))
+        buffer.putInt(CAPABILITIES)
+
+        buffer.rewind()
+
+        send(buffer)
+        }
+    private fun sendAuthenticationResponse(challenge: ByteArray) {
+        val mac=Mac.getInstance("HmacSHA256")
+        mac.init(SecretKeySpec(password.toByteArray(), "HmacSHA256"))
+        val response=mac.doFinal(challenge)
+
+        val buffer=ByteBuffer.allocate(1+response.size)
+
+        buffer.put(b(1)) //ClientMessage::Authenticate
+        buffer.put(response)
+
+        buffer.rewind()
+
+        send(buffer)
+        }
+
+    private fun readString(buffer: ByteBuffer): String {
+        val bytes=ByteArray(buffer.remaining())
+        buffer.get(bytes)
+
+        return String(bytes, Charsets.UTF_8)
+        }
+
     private fun swipeDirectionToInt(direction: SwipeDirection) = when (direction) {
         SwipeDirection.Left -> 0
         SwipeDirection.Right -> 1
@@ -175,4 +297,43 @@ class RBridgeClient(
         }
 
     private fun b(input: Int) = input.toByte()
+
+    companion object {
+
+        const val PROTOCOL_VERSION=1
+        const val CAPABILITY_TRACKPAD=1 shl 1
+        const val CAPABILITIES=CAPABILITY_TRACKPAD
+
+        }
+    }
+
+//Trusts exactly the certificate with the given SHA-256 fingerprint, in the colon-separated hex form the server prints
+class PinnedCertificateTrustManager(fingerprint: String) : X509TrustManager {
+
+    private val fingerprint=normalizeFingerprint(fingerprint)
+
+    override fun checkServerTrusted(chain: Array<X509Certificate>, authType: String) {
+        if (chain.isEmpty())
+        throw CertificateException("The server presented no certificate")
+
+        val digest=MessageDigest.getInstance("SHA-256").digest(chain[0].encoded)
+        val actual=digest.joinToString("") { "%02X".format(it) }
+
+        if (actual!=fingerprint)
+        throw CertificateException("The server certificate fingerprint doesn't match the expected one")
+        }
+
+    override fun checkClientTrusted(chain: Array<X509Certificate>, authType: String) {
+        throw CertificateException("Client certificates are not trusted")
+        }
+
+    override fun getAcceptedIssuers(): Array<X509Certificate> = arrayOf()
+
+    companion object {
+
+        fun normalizeFingerprint(fingerprint: String): String {
+            return fingerprint.filter { it.isLetterOrDigit() }.uppercase()
+            }
+
+        }
     }
@@ -0,0 +1,101 @@
+/*
+* Copyright (C) 2023 Rastislav Kish
+*
+* This program is free software: you can redistribute it and/or modify
+* it under the terms of the GNU General Public License as published by
+* the Free Software Foundation, version 3.
+*
+* This program is distributed in the hope that it will be useful,
+* but WITHOUT ANY WARRANTY; without even the implied warranty of
+* MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+* GNU General Public License for more details.
+*
+* You should have received a copy of the GNU General Public License
+* along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+package com.rastislavkish.rbridge
+
+import java.net.DatagramPacket
+import java.net.DatagramSocket
+import java.net.InetAddress
+import java.net.SocketTimeoutException
+import java.nio.ByteBuffer
+
+class DiscoveredServer(
+    val name: String,
+    val address: String,
+    val port: Int,
+    val protocolVersion: Int,
+    val tls: Boolean,
+    ) {
+
+    override fun toString(): String {
+        return "$name, $address:$port"
+        }
+    }
+
+class ServerDiscovery {
+
+    companion object {
+
+        const val DISCOVERY_PORT=7322
+        private val DISCOVERY_REQUEST="RBRIDGE_DISCOVER".toByteArray(Charsets.US_ASCII)
+        private val DISCOVERY_RESPONSE_HEADER="RBRIDGE_SERVER".toByteArray(Charsets.US_ASCII)
+        private const val DISCOVERY_FLAG_TLS=1
+
+        //Broadcasts a discovery request over the local network and collects the servers answering within the timeout
+        //Blocks the calling thread, so it must not be called from the UI thread
+        fun discover(timeout: Int=2000): List<DiscoveredServer> {
+            val servers=mutableListOf<DiscoveredServer>()
+
+            DatagramSocket().use { socket ->
+                socket.broadcast=true
+                socket.soTimeout=timeout
+
+                socket.send(DatagramPacket(DISCOVERY_REQUEST, DISCOVERY_REQUEST.size, InetAddress.getByName("255.255.255.255"), DISCOVERY_PORT))
+
+                val deadline=System.currentTimeMillis()+timeout
+                val buffer=ByteArray(512)
+
+                while (System.currentTimeMillis()<deadline) {
+                    socket.soTimeout=maxOf(1, (deadline-System.currentTimeMillis()).toInt())
+
+                    val packet=DatagramPacket(buffer, buffer.size)
+                    try {
+                        socket.receive(packet)
+                        }
+                    catch (e: SocketTimeoutException) {
+                        break
+                        }
+
+                    val server=parseResponse(packet) ?: continue
+
+                    if (servers.none { it.address==server.address && it.port==server.port })
+                    servers.add(server)
+                    }
+                }
+
+            return servers
+            }
+
+        //The response is the header, protocol version, flags, port as a big-endian u16 and the server name in UTF-8
+        private fun parseResponse(packet: DatagramPacket): DiscoveredServer? {
+            val headerSize=DISCOVERY_RESPONSE_HEADER.size
+            if (packet.length<headerSize+4)
+            return null
+
+            val data=packet.data.copyOfRange(packet.offset, packet.offset+packet.length)
+            if (!data.copyOfRange(0, headerSize).contentEquals(DISCOVERY_RESPONSE_HEADER))
+            return null
+
+            val buffer=ByteBuffer.wrap(data, headerSize, data.size-headerSize)
+            val protocolVersion=buffer.get().toInt() and 0xFF
+            val flags=buffer.get().toInt() and 0xFF
+            val port=buffer.getShort().toInt() and 0xFFFF
+            val name=String(data, headerSize+4, data.size-headerSize-4, Charsets.UTF_8)
+
+            return DiscoveredServer(name, packet.address.hostAddress ?: return null, port, protocolVersion, (flags and DISCOVERY_FLAG_TLS)!=0)
+            }
+        }
+    }
@@ -14,22 +14,172 @@
 * along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
 
+use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::io::{BufReader, Write};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use enigo::{Enigo, Key, KeyboardControllable};
+use clap::Parser;
 
-use futures_util::{SinkExt, StreamExt};
+use enigo::{Enigo, Key, KeyboardControllable, MouseButton, MouseControllable};
+
+use futures_util::{Sink, SinkExt, StreamExt};
+
+use hmac::{Hmac, Mac};
+
+use log::{debug, error, info, warn, LevelFilter};
+
+use rand::{Rng, RngCore};
 
 use serde::{Serialize, Deserialize};
 
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc};
+use sha2::Sha256;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
 use tungstenite::Message;
 use url::Url;
 
+type HmacSha256=Hmac<Sha256>;
+
+const PROTOCOL_VERSION: u8=1;
+const MINIMUM_PROTOCOL_VERSION: u8=1;
+
+const CAPABILITY_SETTINGS_TRANSFER: u32=1<<0;
+const CAPABILITY_TRACKPAD: u32=1<<1;
+const CAPABILITY_EXTENDED_GESTURES: u32=1<<2;
+const CAPABILITY_DIAGONALS_AND_CIRCLES: u32=1<<3;
+const CAPABILITY_PINCH_AND_ROTATE: u32=1<<4;
+const SERVER_CAPABILITIES: u32=CAPABILITY_SETTINGS_TRANSFER | CAPABILITY_TRACKPAD | CAPABILITY_EXTENDED_GESTURES | CAPABILITY_DIAGONALS_AND_CIRCLES | CAPABILITY_PINCH_AND_ROTATE;
+
+const AUTHENTICATION_CHALLENGE_LENGTH: usize=32;
+const AUTHENTICATION_ATTEMPTS: u32=3;
+const AUTHENTICATION_LOCKOUT: Duration=Duration::from_secs(30);
+const AUTHENTICATION_MAXIMUM_LOCKOUT: Duration=Duration::from_secs(60*60);
+const AUTHENTICATION_FAILURE_MEMORY: Duration=Duration::from_secs(24*60*60);
+//How long a new connection gets to complete the handshakes, the hello and the authentication
+const AUTHENTICATION_TIMEOUT: Duration=Duration::from_secs(30);
+
+const PAIRING_CODE_LENGTH: usize=14;
+//Without characters easily confused with each other, like 0 and O or 1 and I
+const PAIRING_CODE_ALPHABET: &[u8]=b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+const DEFAULT_HOST: &str="0.0.0.0";
+const DEFAULT_PORT: u16=7321;
+
+const ACCEPT_RETRY_DELAY: Duration=Duration::from_millis(100);
+
+const DISCOVERY_PORT: u16=7322;
+const DISCOVERY_REQUEST: &[u8]=b"RBRIDGE_DISCOVER";
+const DISCOVERY_RESPONSE_HEADER: &[u8]=b"RBRIDGE_SERVER";
+const DISCOVERY_FLAG_TLS: u8=1<<0;
+
+const SETTINGS_PATH: &str="settings.json";
+const SETTINGS_POLL_INTERVAL: Duration=Duration::from_secs(1);
+
+const MAXIMUM_REPEAT_COUNT: u32=100;
+//Gestures made while a macro runs wait for it, so neither a single delay nor the backlog of waiting gestures may grow without bounds
+const MAXIMUM_MACRO_DELAY: Duration=Duration::from_secs(10);
+const MAXIMUM_DEFERRED_MESSAGES: usize=64;
+
+const DOWN_RIGHT_SLOT: &str="dr";
+const UP_RIGHT_SLOT: &str="ur";
+
+//Self-signed certificates are kept next to the settings file, so the fingerprint clients pinned doesn't depend on the working directory
+const SELF_SIGNED_CERTIFICATE_FILE: &str="rbridge_certificate.pem";
+const SELF_SIGNED_KEY_FILE: &str="rbridge_key.pem";
+
+/// Executes keyboard shortcuts and other operations for gestures made on RBridge clients.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Arguments {
+    /// Settings file to load, watch for changes and save uploaded settings to
+    #[arg(short, long, default_value=SETTINGS_PATH)]
+    settings: String,
+    /// Addresses to listen on, separated by commas or given repeatedly. An address is an IPv4 or IPv6 address or a host name, optionally with a port, like 192.168.1.5, [::1]:7400 or ws://localhost:7400 [default: 0.0.0.0]
+    #[arg(long, env="RBRIDGE_HOST", value_delimiter=',')]
+    host: Vec<String>,
+    /// Port to listen on for addresses that don't specify one
+    #[arg(short, long, default_value_t=DEFAULT_PORT)]
+    port: u16,
+    /// Name clients discovering servers on the local network see [default: the host name]
+    #[arg(long)]
+    name: Option<String>,
+    /// UDP port to answer discovery requests on
+    #[arg(long, default_value_t=DISCOVERY_PORT)]
+    discovery_port: u16,
+    /// Don't answer discovery requests, clients have to enter the address manually
+    #[arg(long)]
+    no_discovery: bool,
+    /// Password clients authenticate with. Without one, a pairing code is generated on every start
+    #[arg(long, env="RBRIDGE_PASSWORD", hide_env_values=true)]
+    password: Option<String>,
+    /// Read the password from the first line of a file, takes precedence over --password
+    #[arg(long)]
+    password_file: Option<String>,
+    /// PEM certificate chain to serve wss:// with, requires --tls-key
+    #[arg(long, env="RBRIDGE_TLS_CERTIFICATE", requires="tls_key")]
+    tls_certificate: Option<String>,
+    /// PEM private key of the --tls-certificate
+    #[arg(long, env="RBRIDGE_TLS_KEY", requires="tls_certificate")]
+    tls_key: Option<String>,
+    /// Serve wss:// with a self-signed certificate, generated next to the settings file on the first start and reused afterwards
+    #[arg(long, env="RBRIDGE_TLS_SELF_SIGNED", conflicts_with="tls_certificate")]
+    tls_self_signed: bool,
+    /// Log more details, repeat for even more
+    #[arg(short, long, action=clap::ArgAction::Count)]
+    verbose: u8,
+    /// Log only warnings and errors
+    #[arg(short, long, conflicts_with="verbose")]
+    quiet: bool,
+    /// Validate the settings file, report every problem found in it and exit
+    #[arg(long)]
+    check: bool,
+    /// Print a starter settings file and exit
+    #[arg(long)]
+    print_default_settings: bool,
+    }
+impl Arguments {
+
+    fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::Warn;
+            }
+
+        match self.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+            }
+        }
+    }
+
+/// Logs to the standard output without any decoration, showing messages of dependencies only if they're warnings or errors.
+struct ConsoleLogger;
+impl log::Log for ConsoleLogger {
+
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level()<=log::max_level() && (metadata.target().starts_with(env!("CARGO_CRATE_NAME")) || metadata.level()<=log::Level::Warn)
+        }
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            println!("{}", record.args());
+            }
+        }
+    fn flush(&self) {}
+    }
+
+static LOGGER: ConsoleLogger=ConsoleLogger;
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct Action {
     id: i32,
     name: String,
@@ -46,14 +196,18 @@ struct Action {
 impl Action {
 
     fn new(id: i32, name: &str, sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool, forward_shortcut: &str, backward_shortcut: &str) -> Action {
-        Action { id, name: name.to_string(), sticky_ctrl, sticky_shift, sticky_alt, forward_shortcut: forward_shortcut.to_string(), backward_shortcut: backward_shortcut.to_string(), forward_operation: Operation::from_str(forward_shortcut), backward_operation: Operation::from_str(backward_shortcut) }
+        Action { id, name: name.to_string(), sticky_ctrl, sticky_shift, sticky_alt, forward_shortcut: forward_shortcut.to_string(), backward_shortcut: backward_shortcut.to_string(), forward_operation: Operation::from_str(forward_shortcut).unwrap_or(Operation::None), backward_operation: Operation::from_str(backward_shortcut).unwrap_or(Operation::None) }
         }
 
-    fn finalize(&mut self) -> Result<(), String> {
-        self.forward_operation=Operation::from_str(&self.forward_shortcut);
-        self.backward_operation=Operation::from_str(&self.backward_shortcut);
-
-        Ok(())
+    fn finalize(&mut self, path: &str, report: &mut ValidationReport) {
+        match Operation::from_str(&self.forward_shortcut) {
+            Ok(operation) => self.forward_operation=operation,
+            Err(error) => report.error(&format!("{}.forwardShortcut", path), &format!("Action {} ({}): {}", self.id, self.name, error)),
+            }
+        match Operation::from_str(&self.backward_shortcut) {
+            Ok(operation) => self.backward_operation=operation,
+            Err(error) => report.error(&format!("{}.backwardShortcut", path), &format!("Action {} ({}): {}", self.id, self.name, error)),
+            }
         }
     }
 impl Default for Action {
@@ -65,7 +219,7 @@ impl Default for Action {
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct Command {
     id: i32,
     name: String,
@@ -79,13 +233,14 @@ struct Command {
 impl Command {
 
     fn new(id: i32, name: &str, sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool, shortcut: &str) -> Command {
-        Command { id, name: name.to_string(), sticky_ctrl, sticky_shift, sticky_alt, shortcut: shortcut.to_string(), operation: Operation::from_str(shortcut) }
+        Command { id, name: name.to_string(), sticky_ctrl, sticky_shift, sticky_alt, shortcut: shortcut.to_string(), operation: Operation::from_str(shortcut).unwrap_or(Operation::None) }
         }
 
-    fn finalize(&mut self) -> Result<(), String> {
-        self.operation=Operation::from_str(&self.shortcut);
-
-        Ok(())
+    fn finalize(&mut self, path: &str, report: &mut ValidationReport) {
+        match Operation::from_str(&self.shortcut) {
+            Ok(operation) => self.operation=operation,
+            Err(error) => report.error(&format!("{}.shortcut", path), &format!("Command {} ({}): {}", self.id, self.name, error)),
+            }
         }
     }
 impl Default for Command {
@@ -97,7 +252,7 @@ impl Default for Command {
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct Ring {
     id: i32,
     name: String,
@@ -111,26 +266,17 @@ impl Ring {
         Ring { id, name: name.to_string(), actions, action_instances: vec![] }
         }
 
-    fn finalize(&mut self, actions: &Vec<Action>) -> Result<(), String> {
+    fn finalize(&mut self, actions: &[Action], path: &str, report: &mut ValidationReport) {
         let mut action_instances: Vec<Action>=Vec::new();
 
-        for action_id in &self.actions {
-            let mut id_processed=false;
-            for action_instance in actions {
-                if action_instance.id==*action_id {
-                    action_instances.push(action_instance.clone());
-                    id_processed=true;
-                    break;
-                    }
-                }
-            if !id_processed {
-                return Err(format!("Unable to find action with id {}", action_id));
+        for (index, action_id) in self.actions.iter().enumerate() {
+            match actions.iter().find(|action_instance| action_instance.id==*action_id) {
+                Some(action_instance) => action_instances.push(action_instance.clone()),
+                None => report.error(&format!("{}.actions[{}]", path, index), &format!("Ring {} ({}): Unable to find action with id {}", self.id, self.name, action_id)),
                 }
             }
 
         self.action_instances=action_instances;
-
-        Ok(())
         }
     }
 impl Default for Ring {
@@ -142,7 +288,7 @@ impl Default for Ring {
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct SlotBinding {
     id: i32,
     name: String,
@@ -195,31 +341,30 @@ impl SlotBinding {
         self.position=self.default_position;
         }
 
-    fn finalize(&mut self, rings: &Vec<Ring>) -> Result<(), String> {
-        for ring in rings {
-            if ring.id==self.ring {
-                self.ring_instance=ring.clone();
+    fn finalize(&mut self, rings: &[Ring], slot_layout: &SlotLayout, path: &str, report: &mut ValidationReport) {
+        let slots=[slot_layout.slots(self.finger_count, true), slot_layout.slots(self.finger_count, false), slot_layout.diagonal_slots()].concat();
 
-                let mut default_position_set=false;
+        if !slots.contains(&self.slot) {
+            report.error(&format!("{}.slot", path), &format!("Slot binding {} ({}): Unknown slot \"{}\" for {} fingers, use one of {}", self.id, self.name, self.slot, self.finger_count, slots.join(", ")));
+            }
 
-                for (index, action_id) in ring.actions.iter().enumerate() {
-                    if *action_id==self.default_action {
-                        self.default_position=index;
-                        self.position=self.default_position;
-                        default_position_set=true;
-                        break;
-                        }
-                    }
+        let ring=match rings.iter().find(|ring| ring.id==self.ring) {
+            Some(ring) => ring,
+            None => {
+                report.error(&format!("{}.ring", path), &format!("Slot binding {} ({}): Unable to find ring with id {}", self.id, self.name, self.ring));
+                return;
+                },
+            };
 
-                if !default_position_set {
-                    return Err(format!("Error while finalizing SlotBinding {}: Ring {} does nto contain default action {}.", self.id, self.ring, self.default_action));
-                    }
+        self.ring_instance=ring.clone();
 
-                return Ok(());
-                }
+        match ring.actions.iter().position(|action_id| *action_id==self.default_action) {
+            Some(index) => {
+                self.default_position=index;
+                self.position=self.default_position;
+                },
+            None => report.error(&format!("{}.defaultAction", path), &format!("Slot binding {} ({}): Ring {} does not contain default action {}", self.id, self.name, self.ring, self.default_action)),
             }
-
-        return Err(format!("Unable to find ring with id {}", self.ring));
         }
     }
 impl Default for SlotBinding {
@@ -231,7 +376,7 @@ impl Default for SlotBinding {
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct CommandBinding {
     id: i32,
     name: String,
@@ -240,47 +385,94 @@ struct CommandBinding {
     command: i32,
     finger_count: i32,
     modifier_count: i32,
+    region: String,
     #[serde(skip)]
     gesture_shape_instance: GestureShape,
     #[serde(skip)]
+    region_instance: Region,
+    #[serde(skip)]
     command_instance: Command,
     }
 impl CommandBinding {
 
     fn new(id: i32, name: &str, gesture_shape: &str, swipe_directions: Vec<String>, command: i32, finger_count: i32, modifier_count: i32) -> CommandBinding {
-        CommandBinding { id, name: name.to_string(), gesture_shape: gesture_shape.to_string(), swipe_directions, command, finger_count, modifier_count, gesture_shape_instance: GestureShape::Touch, command_instance: Command::default() }
+        CommandBinding { id, name: name.to_string(), gesture_shape: gesture_shape.to_string(), swipe_directions, command, finger_count, modifier_count, region: String::new(), gesture_shape_instance: GestureShape::Touch, region_instance: Region::default(), command_instance: Command::default() }
         }
 
-    fn finalize(&mut self, commands: &Vec<Command>) -> Result<(), String> {
+    fn finalize(&mut self, commands: &[Command], path: &str, report: &mut ValidationReport) {
         self.gesture_shape_instance=match &self.gesture_shape[..] {
-            "Swipe" => {
-                let mut swipe_directions: Vec<Direction>=Vec::new();
-
-                for direction in &self.swipe_directions {
-                    swipe_directions.push(match &direction[..] {
-                        "Left" => Direction::Left,
-                        "Right" => Direction::Right,
-                        "Up" => Direction::Up,
-                        "Down" => Direction::Down,
-                        _ => continue,
-                        });
-                    }
-
-                GestureShape::Swipe(swipe_directions)
-                },
+            "Swipe" => GestureShape::Swipe(self.finalize_swipe_directions(path, report)),
             "Tap" => GestureShape::Tap,
-            _ => GestureShape::Touch,
+            "Touch" => GestureShape::Touch,
+            "LongPress" => GestureShape::LongPress,
+            "DoubleTap" => GestureShape::DoubleTap,
+            "TripleTap" => GestureShape::TripleTap,
+            "HoldSwipe" => GestureShape::HoldSwipe(self.finalize_swipe_directions(path, report)),
+            "ClockwiseCircle" => GestureShape::ClockwiseCircle,
+            "CounterClockwiseCircle" => GestureShape::CounterClockwiseCircle,
+            "PinchIn" => GestureShape::PinchIn,
+            "PinchOut" => GestureShape::PinchOut,
+            "RotateClockwise" => GestureShape::RotateClockwise,
+            "RotateCounterClockwise" => GestureShape::RotateCounterClockwise,
+            gesture_shape => {
+                report.error(&format!("{}.gestureShape", path), &format!("Command binding {} ({}): Unknown gesture shape \"{}\", use Swipe, Tap, Touch, LongPress, DoubleTap, TripleTap, HoldSwipe, ClockwiseCircle, CounterClockwiseCircle, PinchIn, PinchOut, RotateClockwise or RotateCounterClockwise", self.id, self.name, gesture_shape));
+                GestureShape::Touch
+                },
             };
 
-        for command in commands {
-            if command.id==self.command {
-                self.command_instance=command.clone();
+        match Region::from_str(&self.region) {
+            Ok(region) => self.region_instance=region,
+            Err(error) => report.error(&format!("{}.region", path), &format!("Command binding {} ({}): {}", self.id, self.name, error)),
+            }
 
-                return Ok(());
-                }
+        match commands.iter().find(|command| command.id==self.command) {
+            Some(command) => self.command_instance=command.clone(),
+            None => report.error(&format!("{}.command", path), &format!("Command binding {} ({}): Unable to find command with id {}", self.id, self.name, self.command)),
+            }
+        }
+
+    fn finalize_swipe_directions(&self, path: &str, report: &mut ValidationReport) -> Vec<Direction> {
+        let mut swipe_directions: Vec<Direction>=Vec::new();
+
+        for (index, direction) in self.swipe_directions.iter().enumerate() {
+            swipe_directions.push(match &direction[..] {
+                "Left" => Direction::Left,
+                "Right" => Direction::Right,
+                "Up" => Direction::Up,
+                "Down" => Direction::Down,
+                "UpLeft" => Direction::UpLeft,
+                "UpRight" => Direction::UpRight,
+                "DownLeft" => Direction::DownLeft,
+                "DownRight" => Direction::DownRight,
+                _ => {
+                    report.error(&format!("{}.swipeDirections[{}]", path, index), &format!("Command binding {} ({}): Unknown swipe direction \"{}\", use Left, Right, Up, Down, UpLeft, UpRight, DownLeft or DownRight", self.id, self.name, direction));
+                    continue;
+                    },
+                });
+            }
+
+        if self.swipe_directions.is_empty() {
+            report.error(&format!("{}.swipeDirections", path), &format!("Command binding {} ({}): A swipe needs at least one direction", self.id, self.name));
             }
 
-        return Err(format!("Unable to find command with id {}", self.command));
+        swipe_directions
+        }
+
+    fn matches(&self, gesture: &Gesture) -> bool {
+        self.gesture_shape_instance==gesture.shape && self.finger_count==gesture.finger_count && self.modifier_count==gesture.modifier_count && self.region_instance.contains(gesture.start_x, gesture.start_y)
+        }
+    /// Returns the slots whose gestures this binding intercepts, which are the slots along the axis of its swipe overlapping its region, if the swipe forms a slot operation.
+    fn shadowed_slots(&self, slot_layout: &SlotLayout) -> Vec<String> {
+        let gesture=Gesture::new(self.finger_count, self.modifier_count, self.region_instance.left, self.region_instance.top, self.gesture_shape_instance.clone(), 0.0);
+
+        match (&self.gesture_shape_instance, gesture.try_get_slot_operation(slot_layout)) {
+            (GestureShape::Swipe(directions), Ok((slot, _))) => match directions[0] {
+                Direction::Left | Direction::Right => slot_layout.slots_in(self.finger_count, true, &self.region_instance),
+                Direction::Up | Direction::Down => slot_layout.slots_in(self.finger_count, false, &self.region_instance),
+                _ => vec![slot],
+                },
+            _ => Vec::new(),
+            }
         }
     }
 impl Default for CommandBinding {
@@ -290,9 +482,73 @@ impl Default for CommandBinding {
         }
     }
 
+/// A rectangle of the screen in the same coordinates as gesture starting points, from 0 to 1 on both axes.
+#[derive(Clone, Debug, PartialEq)]
+struct Region {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    }
+impl Region {
+
+    fn new(left: f32, top: f32, right: f32, bottom: f32) -> Region {
+        Region { left, top, right, bottom }
+        }
+
+    /// Parses a region, either a named zone of the screen split into thirds, like TopLeft, Center or Bottom, or a rectangle written as left,top,right,bottom. An empty string stands for the whole screen.
+    fn from_str(region: &str) -> Result<Region, String> {
+        let third=1.0/3.0;
+
+        let region=match region.trim() {
+            "" => Region::default(),
+            "TopLeft" => Region::new(0.0, 0.0, third, third),
+            "Top" => Region::new(third, 0.0, 2.0*third, third),
+            "TopRight" => Region::new(2.0*third, 0.0, 1.0, third),
+            "Left" => Region::new(0.0, third, third, 2.0*third),
+            "Center" => Region::new(third, third, 2.0*third, 2.0*third),
+            "Right" => Region::new(2.0*third, third, 1.0, 2.0*third),
+            "BottomLeft" => Region::new(0.0, 2.0*third, third, 1.0),
+            "Bottom" => Region::new(third, 2.0*third, 2.0*third, 1.0),
+            "BottomRight" => Region::new(2.0*third, 2.0*third, 1.0, 1.0),
+            rectangle => {
+                let coordinates: Vec<f32>=rectangle.split(',')
+                .map(|coordinate| coordinate.trim().parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| format!("Unknown region \"{}\", use TopLeft, Top, TopRight, Left, Center, Right, BottomLeft, Bottom, BottomRight or a rectangle like 0,0,0.2,0.2", rectangle))?;
+
+                if coordinates.len()!=4 {
+                    return Err(format!("The rectangle \"{}\" needs four coordinates, left,top,right,bottom", rectangle));
+                    }
+
+                Region::new(coordinates[0], coordinates[1], coordinates[2], coordinates[3])
+                },
+            };
+
+        if !(0.0<=region.left && region.left<region.right && region.right<=1.0 && 0.0<=region.top && region.top<region.bottom && region.bottom<=1.0) {
+            return Err(format!("The rectangle {},{},{},{} must lie within 0 and 1, with left below right and top below bottom", region.left, region.top, region.right, region.bottom));
+            }
+
+        Ok(region)
+        }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.left<=x && x<=self.right && self.top<=y && y<=self.bottom
+        }
+    fn covers(&self, region: &Region) -> bool {
+        self.left<=region.left && region.right<=self.right && self.top<=region.top && region.bottom<=self.bottom
+        }
+    }
+impl Default for Region {
+
+    fn default() -> Region {
+        Region::new(0.0, 0.0, 1.0, 1.0)
+        }
+    }
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct Bindings {
     slot_bindings: Vec<SlotBinding>,
     command_bindings: Vec<CommandBinding>,
@@ -303,15 +559,51 @@ impl Bindings {
         Bindings { slot_bindings, command_bindings }
         }
 
-    fn finalize(&mut self, commands: &Vec<Command>, rings: &Vec<Ring>) -> Result<(), String> {
-        for binding in &mut self.slot_bindings {
-            binding.finalize(rings)?;
+    fn finalize(&mut self, commands: &[Command], rings: &[Ring], slot_layout: &SlotLayout, path: &str, report: &mut ValidationReport) {
+        report.check_unique_ids(&format!("{}.slotBindings", path), "slot binding", self.slot_bindings.iter().map(|binding| binding.id));
+        report.check_unique_ids(&format!("{}.commandBindings", path), "command binding", self.command_bindings.iter().map(|binding| binding.id));
+
+        for (index, binding) in self.slot_bindings.iter_mut().enumerate() {
+            binding.finalize(rings, slot_layout, &format!("{}.slotBindings[{}]", path, index), report);
             }
-        for binding in &mut self.command_bindings {
-            binding.finalize(commands)?;
+        for (index, binding) in self.command_bindings.iter_mut().enumerate() {
+            binding.finalize(commands, &format!("{}.commandBindings[{}]", path, index), report);
             }
 
-        Ok(())
+        self.check_conflicts(slot_layout, path, report);
+        }
+
+    /// Reports bindings that never trigger, or trigger together with others, as warnings.
+    /// Only the first command binding matching a gesture is executed, and command bindings take precedence over slot bindings, while all slot bindings matching a gesture are executed.
+    fn check_conflicts(&self, slot_layout: &SlotLayout, path: &str, report: &mut ValidationReport) {
+        for (index, binding) in self.command_bindings.iter().enumerate() {
+            let shadowing_binding=self.command_bindings[..index].iter()
+            .enumerate()
+            .find(|(_, previous_binding)| previous_binding.gesture_shape_instance==binding.gesture_shape_instance && previous_binding.finger_count==binding.finger_count && previous_binding.modifier_count==binding.modifier_count && previous_binding.region_instance.covers(&binding.region_instance));
+
+            if let Some((previous_index, previous_binding))=shadowing_binding {
+                report.warning(&format!("{}.commandBindings[{}]", path, index), &format!("Command binding {} ({}) never triggers, command binding {} ({}) at {}.commandBindings[{}] is bound to the same gesture in a region covering its own and takes precedence", binding.id, binding.name, previous_binding.id, previous_binding.name, path, previous_index));
+                continue;
+                }
+
+            let shadowed_slots=binding.shadowed_slots(slot_layout);
+
+            for (slot_index, slot_binding) in self.slot_bindings.iter().enumerate() {
+                if shadowed_slots.contains(&slot_binding.slot) && slot_binding.finger_count==binding.finger_count && slot_binding.modifier_count==binding.modifier_count {
+                    report.warning(&format!("{}.slotBindings[{}]", path, slot_index), &format!("Command binding {} ({}) at {}.commandBindings[{}] takes precedence over some gestures of slot binding {} ({})", binding.id, binding.name, path, index, slot_binding.id, slot_binding.name));
+                    }
+                }
+            }
+
+        for (index, binding) in self.slot_bindings.iter().enumerate() {
+            let duplicate_binding=self.slot_bindings[..index].iter()
+            .enumerate()
+            .find(|(_, previous_binding)| previous_binding.slot==binding.slot && previous_binding.finger_count==binding.finger_count && previous_binding.modifier_count==binding.modifier_count);
+
+            if let Some((previous_index, previous_binding))=duplicate_binding {
+                report.warning(&format!("{}.slotBindings[{}]", path, index), &format!("Slot binding {} ({}) is bound to the same slot, finger and modifier count as slot binding {} ({}) at {}.slotBindings[{}], both are executed by the same gestures", binding.id, binding.name, previous_binding.id, previous_binding.name, path, previous_index));
+                }
+            }
         }
     }
 impl Default for Bindings {
@@ -323,7 +615,7 @@ impl Default for Bindings {
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct Scheme {
     id: i32,
     name: String,
@@ -335,10 +627,8 @@ impl Scheme {
         Scheme { id, name: name.to_string(), bindings }
         }
 
-    fn finalize(&mut self, commands: &Vec<Command>, rings: &Vec<Ring>) -> Result<(), String> {
-        self.bindings.finalize(commands, rings)?;
-
-        Ok(())
+    fn finalize(&mut self, commands: &[Command], rings: &[Ring], slot_layout: &SlotLayout, path: &str, report: &mut ValidationReport) {
+        self.bindings.finalize(commands, rings, slot_layout, &format!("{}.bindings", path), report);
         }
     }
 impl Default for Scheme {
@@ -350,576 +640,3401 @@ impl Default for Scheme {
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
+#[serde(rename_all="camelCase")]
 struct Settings {
     actions: Vec<Action>,
     commands: Vec<Command>,
     rings: Vec<Ring>,
     schemes: Vec<Scheme>,
+    slot_layout: SlotLayout,
+    trackpad: TrackpadSettings,
     }
 impl Settings {
 
-    fn new(actions: Vec<Action>, commands: Vec<Command>, rings: Vec<Ring>, schemes: Vec<Scheme>) -> Settings {
-        Settings { actions, commands, rings, schemes }
+    fn new(actions: Vec<Action>, commands: Vec<Command>, rings: Vec<Ring>, schemes: Vec<Scheme>, slot_layout: SlotLayout, trackpad: TrackpadSettings) -> Settings {
+        Settings { actions, commands, rings, schemes, slot_layout, trackpad }
         }
 
+    /// Parses and validates settings. If they contain any errors, the error lists all problems found, otherwise the warnings are logged.
     fn from_json(json: &str) -> Result<Settings, String> {
-        match serde_json::from_str::<Settings>(json) {
-            Ok(mut settings) => {
-                settings.finalize()?;
-                return Ok(settings);
-                },
-            Err(error) => {
-                return Err(error.to_string());
-                }
+        Settings::from_json_with_warnings(json).map(|(settings, _)| settings)
+        }
+    /// Like from_json, but also returns the warnings, so clients uploading settings can be told about them.
+    fn from_json_with_warnings(json: &str) -> Result<(Settings, Vec<SettingsProblem>), String> {
+        let mut settings=serde_json::from_str::<Settings>(json)
+        .map_err(|error| error.to_string())?;
+
+        let report=settings.finalize();
+
+        if report.has_errors() {
+            return Err(report.to_string());
+            }
+
+        for warning in &report.warnings {
+            warn!("Settings warning: {}", warning);
             }
+
+        Ok((settings, report.warnings))
+        }
+
+    /// Settings to start from, printed by --print-default-settings. Horizontal and vertical swipes in the middle of the screen move through the Horizontal and Vertical rings, taps press return, escape and toggle the trackpad mode.
+    fn starter() -> Settings {
+        let actions=vec![
+            Action::new(1, "Tab", false, false, false, "Tab", "Shift+Tab"),
+            Action::new(2, "Arrows", false, false, false, "Down", "Up"),
+            Action::new(3, "Words", false, false, false, "Ctrl+Right", "Ctrl+Left"),
+            Action::new(4, "Windows", false, false, true, "Alt+Tab", "Alt+Shift+Tab"),
+            Action::new(5, "Volume", false, false, false, "VolumeUp", "VolumeDown"),
+            ];
+        let commands=vec![
+            Command::new(10, "Return", false, false, false, "Return"),
+            Command::new(11, "Escape", false, false, false, "Escape"),
+            Command::new(12, "Trackpad", false, false, false, "trackpad:toggle"),
+            ];
+        let rings=vec![
+            Ring::new(20, "Horizontal", vec![1, 3, 4]),
+            Ring::new(21, "Vertical", vec![2, 5]),
+            ];
+        let bindings=Bindings::new(
+            vec![
+                SlotBinding::new(40, "Horizontal", "2h", 20, 1, 1, 0),
+                SlotBinding::new(41, "Vertical", "2v", 21, 2, 1, 0),
+                ],
+            vec![
+                CommandBinding::new(42, "Return", "Tap", vec![], 10, 1, 0),
+                CommandBinding::new(43, "Escape", "Tap", vec![], 11, 2, 0),
+                CommandBinding::new(44, "Trackpad", "Tap", vec![], 12, 3, 0),
+                ],
+            );
+        let schemes=vec![Scheme::new(30, "Default", bindings)];
+
+        Settings::new(actions, commands, rings, schemes, SlotLayout::default(), TrackpadSettings::default())
         }
 
-    fn finalize(&mut self) -> Result<(), String> {
-        for action in &mut self.actions {
-            action.finalize()?;
+    /// Resolves the references between settings objects and parses their operations, collecting every problem found along the way.
+    fn finalize(&mut self) -> ValidationReport {
+        let mut report=ValidationReport::new();
+
+        report.check_unique_ids("actions", "action", self.actions.iter().map(|action| action.id));
+        report.check_unique_ids("commands", "command", self.commands.iter().map(|command| command.id));
+        report.check_unique_ids("rings", "ring", self.rings.iter().map(|ring| ring.id));
+        report.check_unique_ids("schemes", "scheme", self.schemes.iter().map(|scheme| scheme.id));
+
+        self.slot_layout.finalize("slotLayout", &mut report);
+
+        for (index, action) in self.actions.iter_mut().enumerate() {
+            action.finalize(&format!("actions[{}]", index), &mut report);
             }
-        for command in &mut self.commands {
-            command.finalize()?;
+        for (index, command) in self.commands.iter_mut().enumerate() {
+            command.finalize(&format!("commands[{}]", index), &mut report);
             }
-        for ring in &mut self.rings {
-            ring.finalize(&self.actions)?;
+        for (index, ring) in self.rings.iter_mut().enumerate() {
+            ring.finalize(&self.actions, &format!("rings[{}]", index), &mut report);
             }
-        for scheme in &mut self.schemes {
-            scheme.finalize(&self.commands, &self.rings)?;
+        for (index, scheme) in self.schemes.iter_mut().enumerate() {
+            scheme.finalize(&self.commands, &self.rings, &self.slot_layout, &format!("schemes[{}]", index), &mut report);
             }
 
-        Ok(())
+        for (index, action) in self.actions.iter().enumerate() {
+            let owner=format!("Action {} ({})", action.id, action.name);
+            self.check_scheme_reference(&action.forward_operation, &owner, &format!("actions[{}].forwardShortcut", index), &mut report);
+            self.check_scheme_reference(&action.backward_operation, &owner, &format!("actions[{}].backwardShortcut", index), &mut report);
+            }
+        for (index, command) in self.commands.iter().enumerate() {
+            self.check_scheme_reference(&command.operation, &format!("Command {} ({})", command.id, command.name), &format!("commands[{}].shortcut", index), &mut report);
+            }
+
+        report
+        }
+
+    fn check_scheme_reference(&self, operation: &Operation, owner: &str, path: &str, report: &mut ValidationReport) {
+        let operations: Vec<&Operation>=match operation {
+            Operation::Macro(steps) => steps.iter()
+            .filter_map(|step| match step {
+                MacroStep::Operation(operation) => Some(operation),
+                _ => None,
+                })
+            .collect(),
+            Operation::Repeat(_, operation) => return self.check_scheme_reference(operation, owner, path, report),
+            operation => vec![operation],
+            };
+
+        for operation in operations {
+            if let Operation::SwitchScheme(SchemeSwitch::Id(id))=operation {
+                if !self.schemes.iter().any(|scheme| scheme.id==*id) {
+                    report.error(path, &format!("{}: Unable to find scheme with id {}", owner, id));
+                    }
+                }
+            }
         }
 
     }
 impl Default for Settings {
 
     fn default() -> Settings {
-        Settings::new(vec![], vec![], vec![], vec![])
+        Settings::new(vec![], vec![], vec![], vec![], SlotLayout::default(), TrackpadSettings::default())
         }
     }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Splits the screen into zones along each axis, which determine the slot of a swipe by where it starts.
+/// Horizontal swipes are split by their starting y coordinate into 1h, 2h, 3h..., vertical swipes by their starting x coordinate into 1v, 2v, 3v...
+/// An axis without boundaries, as well as any finger count without a layout, has a single slot h or v.
+/// In grid mode, both coordinates count, the horizontal boundaries split the screen into rows and the vertical ones into columns, and each cell has its own horizontal and vertical slot, like r1c2h and r1c2v.
+/// Diagonal swipes are ignored by slots, unless diagonals are enabled, which adds the dr and ur slots for the down-right and up-right diagonal regardless of the finger count and starting point.
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(default)]
-#[serde(rename_all(deserialize="camelCase"))]
-struct Gesture {
-    finger_count: i32,
-    modifier_count: i32,
-    start_x: f32,
-    start_y: f32,
-    shape: GestureShape,
+#[serde(rename_all="camelCase")]
+struct SlotLayout {
+    layouts: Vec<FingerLayout>,
+    diagonals: bool,
     }
-impl Gesture {
+impl SlotLayout {
 
-    fn new(finger_count: i32, modifier_count: i32, start_x: f32, start_y: f32, shape: GestureShape) -> Gesture {
-        Gesture { finger_count, modifier_count, start_x, start_y, shape }
+    fn new(layouts: Vec<FingerLayout>, diagonals: bool) -> SlotLayout {
+        SlotLayout { layouts, diagonals }
         }
 
-    fn try_get_slot_operation(&self) -> Result<(&str, SlotOperation), &str> {
-        use Direction::{Left, Right, Up, Down};
-
-        if let GestureShape::Swipe(directions)=&self.shape {
-            if !(directions.len()>=1 && directions.len()<=3) {
-                return Err("Not a slot gesture");
-                }
+    fn layout(&self, finger_count: i32) -> Option<&FingerLayout> {
+        self.layouts.iter().find(|layout| layout.finger_count==finger_count)
+        }
+    /// Returns the slot of a swipe along the given axis starting at the given point.
+    fn slot(&self, finger_count: i32, horizontal: bool, start_x: f32, start_y: f32) -> String {
+        let axis=if horizontal { "h" } else { "v" };
 
-            let initial_direction=&directions[0];
+        let layout=match self.layout(finger_count) {
+            Some(layout) => layout,
+            None => return axis.to_string(),
+            };
 
-            let slot: &str;
+        if layout.grid {
+            return format!("r{}c{}{}", FingerLayout::zone(&layout.horizontal_boundaries, start_y), FingerLayout::zone(&layout.vertical_boundaries, start_x), axis);
+            }
 
-            if *initial_direction==Left || *initial_direction==Right {
-                if self.finger_count==1 {
-                    slot=if self.start_y<0.2 {
-                        "1h"
-                        }
-                    else if self.start_y<=0.8 {
-                        "2h"
-                        }
-                    else {
-                        "3h"
-                        };
-                    }
-                else {
-                    slot="h";
-                    }
-                }
-            else {
-                if self.finger_count==1 {
-                    slot=if self.start_x<0.2 {
-                        "1v"
-                        }
-                    else if self.start_x<=0.8 {
-                        "2v"
-                        }
-                    else {
-                        "3v"
-                        };
-                    }
-                else {
-                    slot="v";
-                    }
-                }
+        //Horizontal swipes are told apart by where they start vertically and vice versa
+        let (boundaries, position)=if horizontal {
+            (&layout.horizontal_boundaries, start_y)
+            }
+        else {
+            (&layout.vertical_boundaries, start_x)
+            };
 
-            if directions.len()==1 {
-                if *initial_direction==Left || *initial_direction==Up {
-                    return Ok((slot, SlotOperation::Backward));
-                    }
-                else if *initial_direction==Right || *initial_direction==Down {
-                    return Ok((slot, SlotOperation::Forward));
-                    }
-                }
-            else if directions.len()==2 {
-                if *directions==vec![Left, Right] || *directions==vec![Up, Down] {
-                    return Ok((slot, SlotOperation::PreviousAction));
-                    }
-                else if *directions==vec![Right, Left] || *directions==vec![Down, Up] {
-                    return Ok((slot, SlotOperation::NextAction));
-                    }
-                }
-            else if directions.len()==3 {
-                if *directions==vec![Left, Right, Left]
-                || *directions==vec![Right, Left, Right]
-                || *directions==vec![Up, Down, Up]
-                || *directions==vec![Down, Up, Down] {
-                    return Ok((slot, SlotOperation::DefaultAction));
-                    }
-                }
+        if boundaries.is_empty() {
+            return axis.to_string();
             }
 
-        Err("Not a slot gesture")
+        format!("{}{}", FingerLayout::zone(boundaries, position), axis)
         }
-    }
-impl Default for Gesture {
+    /// Returns all slots along the given axis for the finger count.
+    fn slots(&self, finger_count: i32, horizontal: bool) -> Vec<String> {
+        let axis=if horizontal { "h" } else { "v" };
 
-    fn default() -> Gesture {
-        Gesture::new(0, 0, 0.0f32, 0.0f32, GestureShape::Touch)
-        }
-    }
+        let layout=match self.layout(finger_count) {
+            Some(layout) => layout,
+            None => return vec![axis.to_string()],
+            };
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-enum GestureShape {
-    Swipe(Vec<Direction>),
-    Tap,
-    Touch,
-    }
+        if layout.grid {
+            return (1..=layout.horizontal_boundaries.len()+1)
+            .flat_map(|row| (1..=layout.vertical_boundaries.len()+1).map(move |column| format!("r{}c{}{}", row, column, axis)))
+            .collect();
+            }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
-    }
+        let boundaries=if horizontal { &layout.horizontal_boundaries } else { &layout.vertical_boundaries };
 
-enum SlotOperation {
-    Forward,
-    Backward,
-    PreviousAction,
-    NextAction,
-    DefaultAction,
-    }
+        if boundaries.is_empty() {
+            return vec![axis.to_string()];
+            }
 
-#[derive(Clone, Debug)]
-enum Operation {
-    Shortcut(bool, bool, bool, bool, bool, Key),
-    None,
-    }
-impl Operation {
+        (1..=boundaries.len()+1)
+        .map(|zone| format!("{}{}", zone, axis))
+        .collect()
+        }
+    fn diagonal_slots(&self) -> Vec<String> {
+        if !self.diagonals {
+            return Vec::new();
+            }
 
-    fn from_str(input: &str) -> Operation {
-        let processed_input=input.trim().to_string().to_lowercase();
+        vec![DOWN_RIGHT_SLOT.to_string(), UP_RIGHT_SLOT.to_string()]
+        }
+    /// Returns the slots along the given axis that swipes starting within the region fall into.
+    fn slots_in(&self, finger_count: i32, horizontal: bool, region: &Region) -> Vec<String> {
+        let (horizontal_boundaries, vertical_boundaries): (&[f32], &[f32])=match self.layout(finger_count) {
+            Some(layout) => (&layout.horizontal_boundaries, &layout.vertical_boundaries),
+            None => (&[], &[]),
+            };
 
-        if processed_input=="" { return Operation::None; }
+        let xs=FingerLayout::sample_positions(vertical_boundaries, region.left, region.right);
+        let ys=FingerLayout::sample_positions(horizontal_boundaries, region.top, region.bottom);
 
-        //Before parsing a full-fledged shortcut, we need to check if the user doesn't want to just press the meta key, since it can be used both as a modifier and an individual key
-        if processed_input=="meta" {
-            return Operation::Shortcut(false, false, false, false, false, Key::Meta);
-            }
+        let mut slots: Vec<String>=Vec::new();
 
-        let mut ctrl=false;
-        let mut shift=false;
-        let mut alt=false;
-        let mut meta=false;
-        let mut caps_lock=false;
-        let mut key: Option<Key>=None;
+        for x in &xs {
+            for y in &ys {
+                let slot=self.slot(finger_count, horizontal, *x, *y);
 
-        for component in processed_input.split("+") {
-            let mut modifier=true;
-            //Check modifiers
-            match component {
-                "control" | "ctrl" => ctrl=true,
-                "shift" => shift=true,
-                "alt" => alt=true,
-                "meta" => meta=true,
-                "capslock" => caps_lock=true,
-                _ => modifier=false,
+                if !slots.contains(&slot) {
+                    slots.push(slot);
+                    }
                 }
+            }
 
-            if modifier { continue; }
+        slots
+        }
 
-            key=Some(match component {
-                "left" => Key::LeftArrow,
-                "right" => Key::RightArrow,
-                "up" => Key::UpArrow,
-                "down" => Key::DownArrow,
-                "tab" => Key::Tab,
-                "home" => Key::Home,
-                "end" => Key::End,
-                "pageup" => Key::PageUp,
-                "pagedown" => Key::PageDown,
-                "delete" => Key::Delete,
-                "backspace" => Key::Backspace,
-                "return" | "enter" => Key::Return,
-                "space" => Key::Space,
-                "escape" | "esc" => Key::Escape,
-                "f1" => Key::F1,
-                "f2" => Key::F2,
-                "f3" => Key::F3,
-                "f4" => Key::F4,
-                "f5" => Key::F5,
-                "f6" => Key::F6,
-                "f7" => Key::F7,
-                "f8" => Key::F8,
-                "f9" => Key::F9,
-                "f10" => Key::F10,
-                "f11" => Key::F11,
-                "f12" => Key::F12,
-                any => {
-                    if any.chars().count()!=1 {
-                        continue;
-                        }
+    fn finalize(&self, path: &str, report: &mut ValidationReport) {
+        for (index, layout) in self.layouts.iter().enumerate() {
+            let layout_path=format!("{}.layouts[{}]", path, index);
 
-                    Key::Layout(any.chars().next().unwrap())
-                    }
-                });
-            }
+            if self.layouts[..index].iter().any(|previous_layout| previous_layout.finger_count==layout.finger_count) {
+                report.error(&format!("{}.fingerCount", layout_path), &format!("Duplicate slot layout for {} fingers", layout.finger_count));
+                }
 
-        if let Some(key_instance)=key {
-            return Operation::Shortcut(ctrl, shift, alt, meta, caps_lock, key_instance);
+            layout.check_boundaries(&layout.horizontal_boundaries, &format!("{}.horizontalBoundaries", layout_path), report);
+            layout.check_boundaries(&layout.vertical_boundaries, &format!("{}.verticalBoundaries", layout_path), report);
             }
+        }
+    }
+impl Default for SlotLayout {
 
-        Operation::None
+    fn default() -> SlotLayout {
+        SlotLayout::new(vec![FingerLayout::new(1, false, vec![0.2, 0.8], vec![0.2, 0.8])], false)
         }
     }
 
-#[derive(Clone, Debug)]
-enum ClientMessage {
-    Gesture(Gesture),
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[serde(rename_all="camelCase")]
+struct FingerLayout {
+    finger_count: i32,
+    grid: bool,
+    horizontal_boundaries: Vec<f32>,
+    vertical_boundaries: Vec<f32>,
     }
-impl ClientMessage {
+impl FingerLayout {
 
-    fn from_bytes(bytes: &Vec<u8>) -> Result<ClientMessage, String> {
-        if bytes.len()==0 {
-            return Err("empty message".to_string());
-            }
+    fn new(finger_count: i32, grid: bool, horizontal_boundaries: Vec<f32>, vertical_boundaries: Vec<f32>) -> FingerLayout {
+        FingerLayout { finger_count, grid, horizontal_boundaries, vertical_boundaries }
+        }
 
-        match bytes[0] {
-            0 => { //A gesture
-                if bytes.len()<6 {
-                    return Err(format!("{} bytes is not enough to define a gesture client message.", bytes.len()));
-                    }
+    /// Returns the 1-based zone the position falls into. A boundary belongs to the zone following it, except for the last one, which belongs to the zone before it.
+    /// The default layout thus splits at <0.2, 0.2 to 0.8 and >0.8.
+    fn zone(boundaries: &[f32], position: f32) -> usize {
+        1+boundaries.iter()
+        .enumerate()
+        .filter(|&(index, &boundary)| if index+1==boundaries.len() { position>boundary } else { position>=boundary })
+        .count()
+        }
+    /// Returns positions from start to end hitting every zone overlapping that range: its ends, the boundaries within it, and the midpoints between them.
+    fn sample_positions(boundaries: &[f32], start: f32, end: f32) -> Vec<f32> {
+        let edges: Vec<f32>=std::iter::once(start)
+        .chain(boundaries.iter().copied().filter(|boundary| *boundary>start && *boundary<end))
+        .chain(std::iter::once(end))
+        .collect();
+
+        let midpoints: Vec<f32>=edges.windows(2)
+        .map(|pair| (pair[0]+pair[1])/2.0)
+        .collect();
+
+        [edges, midpoints].concat()
+        }
 
-                let finger_count=bytes[1] as i32;
+    fn check_boundaries(&self, boundaries: &[f32], path: &str, report: &mut ValidationReport) {
+        let mut previous_boundary=0.0;
+
+        for (index, &boundary) in boundaries.iter().enumerate() {
+            if !(boundary>previous_boundary && boundary<1.0) {
+                report.error(&format!("{}[{}]", path, index), &format!("Slot layout for {} fingers: Boundary {} must lie between 0 and 1 and be greater than the previous one", self.finger_count, boundary));
+                }
+
+            previous_boundary=boundary;
+            }
+        }
+    }
+impl Default for FingerLayout {
+
+    fn default() -> FingerLayout {
+        FingerLayout::new(1, false, vec![], vec![])
+        }
+    }
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[serde(rename_all="camelCase")]
+struct TrackpadSettings {
+    sensitivity: f32,
+    acceleration: f32,
+    }
+impl TrackpadSettings {
+
+    fn new(sensitivity: f32, acceleration: f32) -> TrackpadSettings {
+        TrackpadSettings { sensitivity, acceleration }
+        }
+    }
+impl Default for TrackpadSettings {
+
+    fn default() -> TrackpadSettings {
+        TrackpadSettings::new(1.0, 0.05)
+        }
+    }
+
+#[derive(Clone, Debug)]
+struct SettingsProblem {
+    path: String,
+    message: String,
+    }
+impl SettingsProblem {
+
+    fn new(path: &str, message: &str) -> SettingsProblem {
+        SettingsProblem { path: path.to_string(), message: message.to_string() }
+        }
+    }
+impl std::fmt::Display for SettingsProblem {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}: {}", self.path, self.message)
+        }
+    }
+
+/// Problems found while finalizing settings. Each one carries a path to the offending value in the settings json, like schemes[0].bindings.slotBindings[2].ring.
+/// Errors make the settings unusable, while warnings point out configurations that work, but likely don't do what was intended.
+#[derive(Clone, Debug)]
+struct ValidationReport {
+    errors: Vec<SettingsProblem>,
+    warnings: Vec<SettingsProblem>,
+    }
+impl ValidationReport {
+
+    fn new() -> ValidationReport {
+        ValidationReport { errors: Vec::new(), warnings: Vec::new() }
+        }
+
+    fn error(&mut self, path: &str, message: &str) {
+        self.errors.push(SettingsProblem::new(path, message));
+        }
+    fn warning(&mut self, path: &str, message: &str) {
+        self.warnings.push(SettingsProblem::new(path, message));
+        }
+    fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+        }
+
+    /// Reports every id of the given collection that was already used by an earlier object of the same collection.
+    fn check_unique_ids(&mut self, path: &str, object_type: &str, ids: impl Iterator<Item=i32>) {
+        let mut seen_ids: Vec<(i32, usize)>=Vec::new();
+
+        for (index, id) in ids.enumerate() {
+            match seen_ids.iter().find(|(seen_id, _)| *seen_id==id) {
+                Some((_, first_index)) => self.error(&format!("{}[{}].id", path, index), &format!("Duplicate {} id {}, already used by {}[{}]", object_type, id, path, first_index)),
+                None => seen_ids.push((id, index)),
+                }
+            }
+        }
+    }
+impl Default for ValidationReport {
+
+    fn default() -> ValidationReport {
+        ValidationReport::new()
+        }
+    }
+impl std::fmt::Display for ValidationReport {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "Found {} error{} and {} warning{} in the settings:", self.errors.len(), if self.errors.len()==1 { "" } else { "s" }, self.warnings.len(), if self.warnings.len()==1 { "" } else { "s" })?;
+
+        for error in &self.errors {
+            write!(formatter, "\n    Error: {}", error)?;
+            }
+        for warning in &self.warnings {
+            write!(formatter, "\n    Warning: {}", warning)?;
+            }
+
+        Ok(())
+        }
+    }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+#[serde(rename_all(deserialize="camelCase"))]
+struct Gesture {
+    finger_count: i32,
+    modifier_count: i32,
+    start_x: f32,
+    start_y: f32,
+    shape: GestureShape,
+    /// How far a pinch or rotation went, in percent of scale change or degrees. Zero for the other shapes.
+    magnitude: f32,
+    }
+impl Gesture {
+
+    fn new(finger_count: i32, modifier_count: i32, start_x: f32, start_y: f32, shape: GestureShape, magnitude: f32) -> Gesture {
+        Gesture { finger_count, modifier_count, start_x, start_y, shape, magnitude }
+        }
+
+    fn try_get_slot_operation(&self, slot_layout: &SlotLayout) -> Result<(String, SlotOperation), &str> {
+        use Direction::{Left, Right, Up, Down, UpLeft, UpRight, DownLeft, DownRight};
+
+        if let GestureShape::Swipe(directions)=&self.shape {
+            if !(directions.len()>=1 && directions.len()<=3) {
+                return Err("Not a slot gesture");
+                }
+
+            //Every slot lies on an axis with a backward and a forward direction, diagonal ones only when enabled in the slot layout
+            let (slot, backward, forward)=match directions[0] {
+                Left | Right => (slot_layout.slot(self.finger_count, true, self.start_x, self.start_y), Left, Right),
+                Up | Down => (slot_layout.slot(self.finger_count, false, self.start_x, self.start_y), Up, Down),
+                UpLeft | DownRight if slot_layout.diagonals => (DOWN_RIGHT_SLOT.to_string(), UpLeft, DownRight),
+                DownLeft | UpRight if slot_layout.diagonals => (UP_RIGHT_SLOT.to_string(), DownLeft, UpRight),
+                _ => return Err("Not a slot gesture"),
+                };
+
+            let slot_operation=match directions[..] {
+                [direction] if direction==backward => SlotOperation::Backward,
+                [direction] if direction==forward => SlotOperation::Forward,
+                [first, second] if first==backward && second==forward => SlotOperation::PreviousAction,
+                [first, second] if first==forward && second==backward => SlotOperation::NextAction,
+                [first, second, third] if first==third && ((first==backward && second==forward) || (first==forward && second==backward)) => SlotOperation::DefaultAction,
+                _ => return Err("Not a slot gesture"),
+                };
+
+            return Ok((slot, slot_operation));
+            }
+
+        Err("Not a slot gesture")
+        }
+    }
+impl Default for Gesture {
+
+    fn default() -> Gesture {
+        Gesture::new(0, 0, 0.0f32, 0.0f32, GestureShape::Touch, 0.0f32)
+        }
+    }
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+enum GestureShape {
+    Swipe(Vec<Direction>),
+    Tap,
+    Touch,
+    LongPress,
+    DoubleTap,
+    TripleTap,
+    /// A swipe started after holding the fingers still for a while.
+    HoldSwipe(Vec<Direction>),
+    ClockwiseCircle,
+    CounterClockwiseCircle,
+    PinchIn,
+    PinchOut,
+    RotateClockwise,
+    RotateCounterClockwise,
+    }
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    }
+
+#[derive(Clone)]
+enum SlotOperation {
+    Forward,
+    Backward,
+    PreviousAction,
+    NextAction,
+    DefaultAction,
+    }
+
+#[derive(Clone, Debug)]
+enum Operation {
+    Shortcut(bool, bool, bool, bool, bool, Key),
+    Text(String),
+    Macro(Vec<MacroStep>),
+    Run(ProcessLaunch),
+    Click(MouseButton, u32),
+    Scroll(i32, i32),
+    MovePointer(i32, i32),
+    MovePointerTo(i32, i32),
+    SwitchTrackpadMode(TrackpadModeSwitch),
+    SwitchScheme(SchemeSwitch),
+    /// Repeats an operation once per the given step of the gesture's magnitude.
+    Repeat(f32, Box<Operation>),
+    None,
+    }
+impl Operation {
+
+    /// Parses an operation, returning a description of the problem if the input isn't a valid one. An empty input is parsed as Operation::None.
+    fn from_str(input: &str) -> Result<Operation, String> {
+        //Text to type is written as text:<text>, everything after the colon is typed verbatim, preserving case and whitespace
+        if let Some(text)=strip_prefix_ignore_case(input.trim_start(), "text:") {
+            return Ok(Operation::Text(text.to_string()));
+            }
+        //Macros are checked before lowercasing as well, since their text steps keep their case
+        if let Some(steps)=strip_prefix_ignore_case(input.trim_start(), "macro:") {
+            return Operation::macro_from_str(steps);
+            }
+        //So are processes, with their paths and arguments
+        if let Some(process)=strip_prefix_ignore_case(input.trim(), "run") {
+            if process.starts_with(':') || process.starts_with('(') {
+                return Operation::run_from_str(process);
+                }
+            }
+        //And repeats, which may wrap any of the above
+        if let Some(repeat)=strip_prefix_ignore_case(input.trim_start(), "repeat:") {
+            return Operation::repeat_from_str(repeat);
+            }
+
+        let processed_input=input.trim().to_string().to_lowercase();
+
+        if processed_input.is_empty() { return Ok(Operation::None); }
+
+        //Scheme switching is written as scheme:next, scheme:previous or scheme:<id>
+        if let Some(target)=processed_input.strip_prefix("scheme:") {
+            return match target.trim() {
+                "next" => Ok(Operation::SwitchScheme(SchemeSwitch::Next)),
+                "previous" | "prev" => Ok(Operation::SwitchScheme(SchemeSwitch::Previous)),
+                id => match id.parse::<i32>() {
+                    Ok(id) => Ok(Operation::SwitchScheme(SchemeSwitch::Id(id))),
+                    Err(_) => Err(format!("\"{}\" is neither next, previous nor a scheme id", id)),
+                    },
+                };
+            }
+
+        //Trackpad mode is switched by trackpad:on, trackpad:off or trackpad:toggle
+        if let Some(switch)=processed_input.strip_prefix("trackpad:") {
+            return match switch.trim() {
+                "on" => Ok(Operation::SwitchTrackpadMode(TrackpadModeSwitch::On)),
+                "off" => Ok(Operation::SwitchTrackpadMode(TrackpadModeSwitch::Off)),
+                "toggle" => Ok(Operation::SwitchTrackpadMode(TrackpadModeSwitch::Toggle)),
+                switch => Err(format!("\"{}\" is not a trackpad mode switch, use on, off or toggle", switch)),
+                };
+            }
+
+        if let Some(operation)=Operation::mouse_operation_from_str(&processed_input) {
+            return operation;
+            }
+
+        //Before parsing a full-fledged shortcut, we need to check if the user doesn't want to just press a modifier, since they can be used both as modifiers and individual keys
+        if let Some(modifier)=Operation::modifier_from_str(&processed_input) {
+            return Ok(Operation::Shortcut(false, false, false, false, false, modifier));
+            }
+
+        let mut ctrl=false;
+        let mut shift=false;
+        let mut alt=false;
+        let mut meta=false;
+        let mut caps_lock=false;
+        let mut key: Option<(&str, Key)>=None;
+
+        for component in processed_input.split('+') {
+            let component=component.trim();
+            let mut modifier=true;
+            //Check modifiers
+            match component {
+                "control" | "ctrl" => ctrl=true,
+                "shift" => shift=true,
+                "alt" => alt=true,
+                "meta" | "super" | "win" | "windows" | "command" | "cmd" => meta=true,
+                "capslock" => caps_lock=true,
+                _ => modifier=false,
+                }
+
+            if modifier { continue; }
+
+            if component.is_empty() {
+                return Err(format!("Shortcut \"{}\" contains an empty key", input.trim()));
+                }
+
+            let key_instance=match Operation::key_from_str(component) {
+                Some(key_instance) => key_instance,
+                None => return Err(format!("Unknown key \"{}\" in shortcut \"{}\"", component, input.trim())),
+                };
+
+            if let Some((previous_component, _))=key {
+                return Err(format!("Shortcut \"{}\" contains more than one non-modifier key, \"{}\" and \"{}\"", input.trim(), previous_component, component));
+                }
+
+            key=Some((component, key_instance));
+            }
+
+        match key {
+            Some((_, key_instance)) => Ok(Operation::Shortcut(ctrl, shift, alt, meta, caps_lock, key_instance)),
+            None => Err(format!("Shortcut \"{}\" contains only modifiers, but no key", input.trim())),
+            }
+        }
+
+    /// Parses mouse operations: click:<button>, doubleclick:<button>, scroll:<direction>[:<lines>], move:<x>,<y> for relative and moveto:<x>,<y> for absolute pointer movement.
+    /// Buttons are left, right and middle, directions are up, down, left and right.
+    /// Returns None if the input isn't a mouse operation, and Some(Err(...)) if it is, but can't be parsed.
+    fn mouse_operation_from_str(input: &str) -> Option<Result<Operation, String>> {
+        let (name, parameters)=input.split_once(':')?;
+        let parameters=parameters.trim();
+
+        let operation=match name.trim() {
+            "click" | "doubleclick" => {
+                let button=match parameters {
+                    "left" => MouseButton::Left,
+                    "right" => MouseButton::Right,
+                    "middle" => MouseButton::Middle,
+                    button => return Some(Err(format!("Unknown mouse button \"{}\", use left, right or middle", button))),
+                    };
+
+                Operation::Click(button, if name.trim()=="doubleclick" { 2 } else { 1 })
+                },
+            "scroll" => {
+                let (direction, lines)=match parameters.split_once(':') {
+                    Some((direction, lines)) => match lines.trim().parse::<i32>() {
+                        Ok(lines) => (direction.trim(), lines),
+                        Err(_) => return Some(Err(format!("\"{}\" is not a valid number of lines to scroll", lines.trim()))),
+                        },
+                    None => (parameters, 1),
+                    };
+
+                match direction {
+                    "up" => Operation::Scroll(0, -lines),
+                    "down" => Operation::Scroll(0, lines),
+                    "left" => Operation::Scroll(-lines, 0),
+                    "right" => Operation::Scroll(lines, 0),
+                    direction => return Some(Err(format!("Unknown scroll direction \"{}\", use up, down, left or right", direction))),
+                    }
+                },
+            "move" | "moveto" => {
+                let coordinates=parameters.split_once(',')
+                .and_then(|(x, y)| Some((x.trim().parse::<i32>().ok()?, y.trim().parse::<i32>().ok()?)));
+
+                match (name.trim(), coordinates) {
+                    ("move", Some((x, y))) => Operation::MovePointer(x, y),
+                    ("moveto", Some((x, y))) => Operation::MovePointerTo(x, y),
+                    _ => return Some(Err(format!("\"{}\" are not valid pointer coordinates, use <x>,<y>", parameters))),
+                    }
+                },
+            _ => return None,
+            };
+
+        Some(Ok(operation))
+        }
+
+    /// Parses a macro written as macro:<step>;<step>;..., where a step is a shortcut, text:<text>, delay:<milliseconds> of at most MAXIMUM_MACRO_DELAY, down:<key> or up:<key>.
+    /// Steps are trimmed, a literal semicolon or backslash is written as \; or \\.
+    fn macro_from_str(input: &str) -> Result<Operation, String> {
+        let mut steps: Vec<MacroStep>=Vec::new();
+
+        for step in split_macro_steps(input) {
+            let step=step.trim();
+
+            if step.is_empty() {
+                continue;
+                }
+
+            let lowercase_step=step.to_lowercase();
+
+            let macro_step=if let Some(delay)=lowercase_step.strip_prefix("delay:") {
+                match delay.trim().parse::<u64>() {
+                    Ok(milliseconds) if Duration::from_millis(milliseconds)<=MAXIMUM_MACRO_DELAY => MacroStep::Delay(Duration::from_millis(milliseconds)),
+                    Ok(_) => return Err(format!("The macro delay {} exceeds the maximum of {} milliseconds", delay.trim(), MAXIMUM_MACRO_DELAY.as_millis())),
+                    Err(_) => return Err(format!("\"{}\" is not a valid macro delay in milliseconds", delay.trim())),
+                    }
+                }
+            else if let Some(key)=lowercase_step.strip_prefix("down:") {
+                match Operation::modifier_or_key_from_str(key.trim()) {
+                    Some(key) => MacroStep::KeyDown(key),
+                    None => return Err(format!("Unknown key \"{}\" in macro step \"{}\"", key.trim(), step)),
+                    }
+                }
+            else if let Some(key)=lowercase_step.strip_prefix("up:") {
+                match Operation::modifier_or_key_from_str(key.trim()) {
+                    Some(key) => MacroStep::KeyUp(key),
+                    None => return Err(format!("Unknown key \"{}\" in macro step \"{}\"", key.trim(), step)),
+                    }
+                }
+            else {
+                match Operation::from_str(step) {
+                    Ok(Operation::Macro(_)) => return Err("Macros can't be nested".to_string()),
+                    Ok(Operation::Repeat(_, _)) => return Err("Repeats can't be used within macros, repeat the whole macro instead".to_string()),
+                    Ok(operation) => MacroStep::Operation(operation),
+                    Err(error) => return Err(format!("Invalid macro step \"{}\": {}", step, error)),
+                    }
+                };
+
+            steps.push(macro_step);
+            }
+
+        if steps.is_empty() {
+            return Err("Macro contains no steps".to_string());
+            }
+
+        Ok(Operation::Macro(steps))
+        }
+
+    /// Parses the part of repeat:<step>:<operation> after the colon. The operation is executed once per step of the gesture's magnitude, so repeat:10:ctrl+plus zooms in once per 10% of a pinch.
+    /// Macros and processes can't be repeated.
+    fn repeat_from_str(input: &str) -> Result<Operation, String> {
+        let (step, operation)=match input.split_once(':') {
+            Some(parts) => parts,
+            None => return Err(format!("\"{}\" is missing the operation to repeat, use repeat:<step>:<operation>", input.trim())),
+            };
+
+        let step=match step.trim().parse::<f32>() {
+            Ok(step) if step>0.0 => step,
+            _ => return Err(format!("\"{}\" is not a valid repeat step, use a positive number", step.trim())),
+            };
+
+        //A single pinch could otherwise hold up the executor with a hundred macros or launch a hundred processes
+        match Operation::from_str(operation)? {
+            Operation::Repeat(_, _) => Err("Repeats can't be nested".to_string()),
+            Operation::Macro(_) => Err("Macros can't be repeated".to_string()),
+            Operation::Run(_) => Err("Processes can't be launched repeatedly".to_string()),
+            operation => Ok(Operation::Repeat(step, Box::new(operation))),
+            }
+        }
+    /// Returns how many times a repeat executes its operation for a gesture of the given magnitude. Gestures without a magnitude, as well as small ones, execute it once.
+    fn repeat_count(step: f32, magnitude: f32) -> u32 {
+        ((magnitude/step).round() as u32).clamp(1, MAXIMUM_REPEAT_COUNT)
+        }
+
+    /// Parses the part of run:<command line> or run(<options>):<command line> after the run keyword.
+    /// Options are separated by commas, dir=<path> sets the working directory and announce sends the process output to clients.
+    /// The command line is split on whitespace, with quotes grouping arguments and backslashes escaping characters.
+    fn run_from_str(input: &str) -> Result<Operation, String> {
+        let mut working_directory: Option<String>=None;
+        let mut announce_output=false;
+
+        let command_line=if let Some(options)=input.strip_prefix('(') {
+            let (options, command_line)=match options.split_once("):") {
+                Some(parts) => parts,
+                None => return Err("Run options must be closed by \"):\"".to_string()),
+                };
+
+            for option in options.split(',') {
+                let option=option.trim();
+
+                if option.eq_ignore_ascii_case("announce") {
+                    announce_output=true;
+                    }
+                else if let Some(path)=strip_prefix_ignore_case(option, "dir=") {
+                    working_directory=Some(path.trim().to_string());
+                    }
+                else if !option.is_empty() {
+                    return Err(format!("Unknown run option \"{}\"", option));
+                    }
+                }
+
+            command_line
+            }
+        else {
+            &input[1..]
+            };
+
+        let mut arguments=match split_command_line(command_line) {
+            Some(arguments) if !arguments.is_empty() => arguments,
+            Some(_) => return Err("Run contains no program to launch".to_string()),
+            None => return Err(format!("Unbalanced quotes or trailing backslash in command line \"{}\"", command_line.trim())),
+            };
+        let program=arguments.remove(0);
+
+        Ok(Operation::Run(ProcessLaunch { program, arguments, working_directory, announce_output }))
+        }
+
+    /// Parses a key name. Every key enigo exposes on the current platform is named by its lowercased variant name, with a few friendlier aliases meaning the same key on every platform, single characters are typed by the keyboard layout and raw:<keycode> presses a platform-specific keycode, given in decimal or with a 0x prefix in hexadecimal.
+    fn key_from_str(name: &str) -> Option<Key> {
+        if let Some(keycode)=name.strip_prefix("raw:") {
+            let keycode=keycode.trim();
+            let parsed_keycode=match keycode.strip_prefix("0x") {
+                Some(hexadecimal) => u16::from_str_radix(hexadecimal, 16),
+                None => keycode.parse::<u16>(),
+                };
+
+            return parsed_keycode.ok().map(Key::Raw);
+            }
+
+        Some(match name {
+            #[cfg(target_os = "windows")]
+            "abntc1" => Key::AbntC1,
+            #[cfg(target_os = "windows")]
+            "abntc2" => Key::AbntC2,
+            #[cfg(target_os = "windows")]
+            "accept" => Key::Accept,
+            #[cfg(target_os = "windows")]
+            "add" => Key::Add,
+            //enigo has no keypad keys on linux, they're pressed by their evdev keycodes, which every current X server uses
+            //Like on a physical keyboard, the keypad digits only type digits while Num Lock is on
+            #[cfg(target_os = "linux")]
+            "add" => Key::Raw(86),
+            #[cfg(target_os = "windows")]
+            "apps" | "menu" => Key::Apps,
+            //enigo sends the Menu keysym for LMenu on linux, which is the context menu key, while on windows LMenu is the left Alt
+            #[cfg(target_os = "linux")]
+            "apps" | "menu" => Key::LMenu,
+            #[cfg(target_os = "windows")]
+            "attn" => Key::Attn,
+            "backspace" => Key::Backspace,
+            #[cfg(target_os = "linux")]
+            "begin" => Key::Begin,
+            #[cfg(target_os = "linux")]
+            "break" => Key::Break,
+            #[cfg(target_os = "windows")]
+            "browserback" => Key::BrowserBack,
+            #[cfg(target_os = "windows")]
+            "browserfavorites" => Key::BrowserFavorites,
+            #[cfg(target_os = "windows")]
+            "browserforward" => Key::BrowserForward,
+            #[cfg(target_os = "windows")]
+            "browserhome" => Key::BrowserHome,
+            #[cfg(target_os = "windows")]
+            "browserrefresh" => Key::BrowserRefresh,
+            #[cfg(target_os = "windows")]
+            "browsersearch" => Key::BrowserSearch,
+            #[cfg(target_os = "windows")]
+            "browserstop" => Key::BrowserStop,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "cancel" => Key::Cancel,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "clear" => Key::Clear,
+            #[cfg(target_os = "windows")]
+            "convert" => Key::Convert,
+            #[cfg(target_os = "windows")]
+            "crsel" => Key::Crsel,
+            #[cfg(target_os = "windows")]
+            "dbealphanumeric" => Key::DBEAlphanumeric,
+            #[cfg(target_os = "windows")]
+            "dbecodeinput" => Key::DBECodeinput,
+            #[cfg(target_os = "windows")]
+            "dbedeterminestring" => Key::DBEDetermineString,
+            #[cfg(target_os = "windows")]
+            "dbeenterdlgconversionmode" => Key::DBEEnterDLGConversionMode,
+            #[cfg(target_os = "windows")]
+            "dbeenterimeconfigmode" => Key::DBEEnterIMEConfigMode,
+            #[cfg(target_os = "windows")]
+            "dbeenterwordregistermode" => Key::DBEEnterWordRegisterMode,
+            #[cfg(target_os = "windows")]
+            "dbeflushstring" => Key::DBEFlushString,
+            #[cfg(target_os = "windows")]
+            "dbehiragana" => Key::DBEHiragana,
+            #[cfg(target_os = "windows")]
+            "dbekatakana" => Key::DBEKatakana,
+            #[cfg(target_os = "windows")]
+            "dbenocodepoint" => Key::DBENoCodepoint,
+            #[cfg(target_os = "windows")]
+            "dbenoroman" => Key::DBENoRoman,
+            #[cfg(target_os = "windows")]
+            "dberoman" => Key::DBERoman,
+            #[cfg(target_os = "windows")]
+            "dbesbcschar" => Key::DBESBCSChar,
+            #[cfg(target_os = "windows")]
+            "dbeschar" => Key::DBESChar,
+            #[cfg(target_os = "windows")]
+            "decimal" => Key::Decimal,
+            #[cfg(target_os = "linux")]
+            "decimal" => Key::Raw(91),
+            "delete" => Key::Delete,
+            #[cfg(target_os = "windows")]
+            "divide" => Key::Divide,
+            #[cfg(target_os = "linux")]
+            "divide" => Key::Raw(106),
+            "down" | "downarrow" => Key::DownArrow,
+            "end" => Key::End,
+            #[cfg(target_os = "windows")]
+            "ereof" => Key::Ereof,
+            "escape" | "esc" => Key::Escape,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "execute" => Key::Execute,
+            #[cfg(target_os = "windows")]
+            "exsel" => Key::Exsel,
+            "f1" => Key::F1,
+            "f2" => Key::F2,
+            "f3" => Key::F3,
+            "f4" => Key::F4,
+            "f5" => Key::F5,
+            "f6" => Key::F6,
+            "f7" => Key::F7,
+            "f8" => Key::F8,
+            "f9" => Key::F9,
+            "f10" => Key::F10,
+            "f11" => Key::F11,
+            "f12" => Key::F12,
+            "f13" => Key::F13,
+            "f14" => Key::F14,
+            "f15" => Key::F15,
+            "f16" => Key::F16,
+            "f17" => Key::F17,
+            "f18" => Key::F18,
+            "f19" => Key::F19,
+            "f20" => Key::F20,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "f21" => Key::F21,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "f22" => Key::F22,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "f23" => Key::F23,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "f24" => Key::F24,
+            #[cfg(target_os = "linux")]
+            "f25" => Key::F25,
+            #[cfg(target_os = "linux")]
+            "f26" => Key::F26,
+            #[cfg(target_os = "linux")]
+            "f27" => Key::F27,
+            #[cfg(target_os = "linux")]
+            "f28" => Key::F28,
+            #[cfg(target_os = "linux")]
+            "f29" => Key::F29,
+            #[cfg(target_os = "linux")]
+            "f30" => Key::F30,
+            #[cfg(target_os = "linux")]
+            "f31" => Key::F31,
+            #[cfg(target_os = "linux")]
+            "f32" => Key::F32,
+            #[cfg(target_os = "linux")]
+            "f33" => Key::F33,
+            #[cfg(target_os = "linux")]
+            "f34" => Key::F34,
+            #[cfg(target_os = "linux")]
+            "f35" => Key::F35,
+            #[cfg(target_os = "macos")]
+            "function" => Key::Function,
+            #[cfg(target_os = "windows")]
+            "final" => Key::Final,
+            #[cfg(target_os = "linux")]
+            "find" => Key::Find,
+            #[cfg(target_os = "windows")]
+            "gamepada" => Key::GamepadA,
+            #[cfg(target_os = "windows")]
+            "gamepadb" => Key::GamepadB,
+            #[cfg(target_os = "windows")]
+            "gamepaddpaddown" => Key::GamepadDPadDown,
+            #[cfg(target_os = "windows")]
+            "gamepaddpadleft" => Key::GamepadDPadLeft,
+            #[cfg(target_os = "windows")]
+            "gamepaddpadright" => Key::GamepadDPadRight,
+            #[cfg(target_os = "windows")]
+            "gamepaddpadup" => Key::GamepadDPadUp,
+            #[cfg(target_os = "windows")]
+            "gamepadleftshoulder" => Key::GamepadLeftShoulder,
+            #[cfg(target_os = "windows")]
+            "gamepadleftthumbstickbutton" => Key::GamepadLeftThumbstickButton,
+            #[cfg(target_os = "windows")]
+            "gamepadleftthumbstickdown" => Key::GamepadLeftThumbstickDown,
+            #[cfg(target_os = "windows")]
+            "gamepadleftthumbstickleft" => Key::GamepadLeftThumbstickLeft,
+            #[cfg(target_os = "windows")]
+            "gamepadleftthumbstickright" => Key::GamepadLeftThumbstickRight,
+            #[cfg(target_os = "windows")]
+            "gamepadleftthumbstickup" => Key::GamepadLeftThumbstickUp,
+            #[cfg(target_os = "windows")]
+            "gamepadlefttrigger" => Key::GamepadLeftTrigger,
+            #[cfg(target_os = "windows")]
+            "gamepadmenu" => Key::GamepadMenu,
+            #[cfg(target_os = "windows")]
+            "gamepadrightshoulder" => Key::GamepadRightShoulder,
+            #[cfg(target_os = "windows")]
+            "gamepadrightthumbstickbutton" => Key::GamepadRightThumbstickButton,
+            #[cfg(target_os = "windows")]
+            "gamepadrightthumbstickdown" => Key::GamepadRightThumbstickDown,
+            #[cfg(target_os = "windows")]
+            "gamepadrightthumbstickleft" => Key::GamepadRightThumbstickLeft,
+            #[cfg(target_os = "windows")]
+            "gamepadrightthumbstickright" => Key::GamepadRightThumbstickRight,
+            #[cfg(target_os = "windows")]
+            "gamepadrightthumbstickup" => Key::GamepadRightThumbstickUp,
+            #[cfg(target_os = "windows")]
+            "gamepadrighttrigger" => Key::GamepadRightTrigger,
+            #[cfg(target_os = "windows")]
+            "gamepadview" => Key::GamepadView,
+            #[cfg(target_os = "windows")]
+            "gamepadx" => Key::GamepadX,
+            #[cfg(target_os = "windows")]
+            "gamepady" => Key::GamepadY,
+            #[cfg(target_os = "windows")]
+            "hangeul" => Key::Hangeul,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "hangul" => Key::Hangul,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "hanja" => Key::Hanja,
+            "help" => Key::Help,
+            "home" => Key::Home,
+            #[cfg(target_os = "windows")]
+            "ico00" => Key::Ico00,
+            #[cfg(target_os = "windows")]
+            "icoclear" => Key::IcoClear,
+            #[cfg(target_os = "windows")]
+            "icohelp" => Key::IcoHelp,
+            #[cfg(target_os = "windows")]
+            "imeoff" => Key::IMEOff,
+            #[cfg(target_os = "windows")]
+            "imeon" => Key::IMEOn,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "insert" | "ins" => Key::Insert,
+            #[cfg(target_os = "windows")]
+            "junja" => Key::Junja,
+            #[cfg(target_os = "windows")]
+            "kana" => Key::Kana,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "kanji" => Key::Kanji,
+            #[cfg(target_os = "windows")]
+            "launchapp1" => Key::LaunchApp1,
+            #[cfg(target_os = "windows")]
+            "launchapp2" => Key::LaunchApp2,
+            #[cfg(target_os = "windows")]
+            "launchmail" => Key::LaunchMail,
+            #[cfg(target_os = "windows")]
+            "launchmediaselect" => Key::LaunchMediaSelect,
+            #[cfg(target_os = "macos")]
+            "launchpad" => Key::Launchpad,
+            #[cfg(target_os = "windows")]
+            "lbutton" => Key::LButton,
+            "lcontrol" | "lctrl" => Key::LControl,
+            "left" | "leftarrow" => Key::LeftArrow,
+            #[cfg(target_os = "linux")]
+            "linefeed" => Key::Linefeed,
+            #[cfg(target_os = "windows")]
+            "lmenu" => Key::LMenu,
+            "lshift" => Key::LShift,
+            #[cfg(target_os = "windows")]
+            "lwin" => Key::LWin,
+            #[cfg(target_os = "windows")]
+            "mbutton" => Key::MButton,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "medianexttrack" | "nexttrack" => Key::MediaNextTrack,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "mediaplaypause" | "playpause" => Key::MediaPlayPause,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "mediaprevtrack" | "prevtrack" | "previoustrack" => Key::MediaPrevTrack,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "mediastop" => Key::MediaStop,
+            #[cfg(target_os = "macos")]
+            "missioncontrol" => Key::MissionControl,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "modechange" => Key::ModeChange,
+            #[cfg(target_os = "windows")]
+            "multiply" => Key::Multiply,
+            #[cfg(target_os = "linux")]
+            "multiply" => Key::Raw(63),
+            #[cfg(target_os = "windows")]
+            "navigationaccept" => Key::NavigationAccept,
+            #[cfg(target_os = "windows")]
+            "navigationcancel" => Key::NavigationCancel,
+            #[cfg(target_os = "windows")]
+            "navigationdown" => Key::NavigationDown,
+            #[cfg(target_os = "windows")]
+            "navigationleft" => Key::NavigationLeft,
+            #[cfg(target_os = "windows")]
+            "navigationmenu" => Key::NavigationMenu,
+            #[cfg(target_os = "windows")]
+            "navigationright" => Key::NavigationRight,
+            #[cfg(target_os = "windows")]
+            "navigationup" => Key::NavigationUp,
+            #[cfg(target_os = "windows")]
+            "navigationview" => Key::NavigationView,
+            #[cfg(target_os = "windows")]
+            "noname" => Key::NoName,
+            #[cfg(target_os = "windows")]
+            "nonconvert" => Key::NonConvert,
+            #[cfg(target_os = "windows")]
+            "none" => Key::None,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "numlock" => Key::Numlock,
+            #[cfg(target_os = "windows")]
+            "numpad0" => Key::Numpad0,
+            #[cfg(target_os = "linux")]
+            "numpad0" => Key::Raw(90),
+            #[cfg(target_os = "windows")]
+            "numpad1" => Key::Numpad1,
+            #[cfg(target_os = "linux")]
+            "numpad1" => Key::Raw(87),
+            #[cfg(target_os = "windows")]
+            "numpad2" => Key::Numpad2,
+            #[cfg(target_os = "linux")]
+            "numpad2" => Key::Raw(88),
+            #[cfg(target_os = "windows")]
+            "numpad3" => Key::Numpad3,
+            #[cfg(target_os = "linux")]
+            "numpad3" => Key::Raw(89),
+            #[cfg(target_os = "windows")]
+            "numpad4" => Key::Numpad4,
+            #[cfg(target_os = "linux")]
+            "numpad4" => Key::Raw(83),
+            #[cfg(target_os = "windows")]
+            "numpad5" => Key::Numpad5,
+            #[cfg(target_os = "linux")]
+            "numpad5" => Key::Raw(84),
+            #[cfg(target_os = "windows")]
+            "numpad6" => Key::Numpad6,
+            #[cfg(target_os = "linux")]
+            "numpad6" => Key::Raw(85),
+            #[cfg(target_os = "windows")]
+            "numpad7" => Key::Numpad7,
+            #[cfg(target_os = "linux")]
+            "numpad7" => Key::Raw(79),
+            #[cfg(target_os = "windows")]
+            "numpad8" => Key::Numpad8,
+            #[cfg(target_os = "linux")]
+            "numpad8" => Key::Raw(80),
+            #[cfg(target_os = "windows")]
+            "numpad9" => Key::Numpad9,
+            #[cfg(target_os = "linux")]
+            "numpad9" => Key::Raw(81),
+            #[cfg(target_os = "windows")]
+            "oem1" => Key::OEM1,
+            #[cfg(target_os = "windows")]
+            "oem102" => Key::OEM102,
+            #[cfg(target_os = "windows")]
+            "oem2" => Key::OEM2,
+            #[cfg(target_os = "windows")]
+            "oem3" => Key::OEM3,
+            #[cfg(target_os = "windows")]
+            "oem4" => Key::OEM4,
+            #[cfg(target_os = "windows")]
+            "oem5" => Key::OEM5,
+            #[cfg(target_os = "windows")]
+            "oem6" => Key::OEM6,
+            #[cfg(target_os = "windows")]
+            "oem7" => Key::OEM7,
+            #[cfg(target_os = "windows")]
+            "oem8" => Key::OEM8,
+            #[cfg(target_os = "windows")]
+            "oemattn" => Key::OEMAttn,
+            #[cfg(target_os = "windows")]
+            "oemauto" => Key::OEMAuto,
+            #[cfg(target_os = "windows")]
+            "oemax" => Key::OEMAx,
+            #[cfg(target_os = "windows")]
+            "oembacktab" => Key::OEMBacktab,
+            #[cfg(target_os = "windows")]
+            "oemclear" => Key::OEMClear,
+            #[cfg(target_os = "windows")]
+            "oemcomma" => Key::OEMComma,
+            #[cfg(target_os = "windows")]
+            "oemcopy" => Key::OEMCopy,
+            #[cfg(target_os = "windows")]
+            "oemcusel" => Key::OEMCusel,
+            #[cfg(target_os = "windows")]
+            "oemenlw" => Key::OEMEnlw,
+            #[cfg(target_os = "windows")]
+            "oemfinish" => Key::OEMFinish,
+            #[cfg(target_os = "windows")]
+            "oemfjjisho" => Key::OEMFJJisho,
+            #[cfg(target_os = "windows")]
+            "oemfjloya" => Key::OEMFJLoya,
+            #[cfg(target_os = "windows")]
+            "oemfjmasshou" => Key::OEMFJMasshou,
+            #[cfg(target_os = "windows")]
+            "oemfjroya" => Key::OEMFJRoya,
+            #[cfg(target_os = "windows")]
+            "oemfjtouroku" => Key::OEMFJTouroku,
+            #[cfg(target_os = "windows")]
+            "oemjump" => Key::OEMJump,
+            #[cfg(target_os = "windows")]
+            "oemminus" => Key::OEMMinus,
+            #[cfg(target_os = "windows")]
+            "oemnecequal" => Key::OEMNECEqual,
+            #[cfg(target_os = "windows")]
+            "oempa1" => Key::OEMPA1,
+            #[cfg(target_os = "windows")]
+            "oempa2" => Key::OEMPA2,
+            #[cfg(target_os = "windows")]
+            "oempa3" => Key::OEMPA3,
+            #[cfg(target_os = "windows")]
+            "oemperiod" => Key::OEMPeriod,
+            #[cfg(target_os = "windows")]
+            "oemplus" => Key::OEMPlus,
+            #[cfg(target_os = "windows")]
+            "oemreset" => Key::OEMReset,
+            #[cfg(target_os = "windows")]
+            "oemwsctrl" => Key::OEMWsctrl,
+            "option" => Key::Option,
+            #[cfg(target_os = "windows")]
+            "pa1" => Key::PA1,
+            #[cfg(target_os = "windows")]
+            "packet" => Key::Packet,
+            "pagedown" => Key::PageDown,
+            "pageup" => Key::PageUp,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "pause" => Key::Pause,
+            #[cfg(target_os = "windows")]
+            "play" => Key::Play,
+            //The plus sign separates shortcut components, so it needs a name of its own
+            "plus" => Key::Layout('+'),
+            //Print is the rarely present Print key on windows, but the Print Screen key on linux
+            #[cfg(target_os = "windows")]
+            "print" => Key::Print,
+            #[cfg(target_os = "linux")]
+            "printscreen" | "snapshot" => Key::Print,
+            #[cfg(target_os = "windows")]
+            "processkey" => Key::Processkey,
+            #[cfg(target_os = "windows")]
+            "rbutton" => Key::RButton,
+            #[cfg(target_os = "macos")]
+            "rcommand" => Key::RCommand,
+            "rcontrol" | "rctrl" => Key::RControl,
+            #[cfg(target_os = "linux")]
+            "redo" => Key::Redo,
+            "return" | "enter" => Key::Return,
+            "right" | "rightarrow" => Key::RightArrow,
+            #[cfg(target_os = "windows")]
+            "rmenu" => Key::RMenu,
+            #[cfg(target_os = "macos")]
+            "roption" => Key::ROption,
+            "rshift" => Key::RShift,
+            #[cfg(target_os = "windows")]
+            "rwin" => Key::RWin,
+            #[cfg(target_os = "windows")]
+            "scroll" => Key::Scroll,
+            #[cfg(target_os = "linux")]
+            "scrolllock" => Key::ScrollLock,
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            "select" => Key::Select,
+            #[cfg(target_os = "linux")]
+            "scriptswitch" => Key::ScriptSwitch,
+            #[cfg(target_os = "windows")]
+            "separator" => Key::Separator,
+            #[cfg(target_os = "linux")]
+            "shiftlock" => Key::ShiftLock,
+            #[cfg(target_os = "windows")]
+            "sleep" => Key::Sleep,
+            #[cfg(target_os = "windows")]
+            "snapshot" | "printscreen" => Key::Snapshot,
+            "space" => Key::Space,
+            #[cfg(target_os = "windows")]
+            "subtract" => Key::Subtract,
+            #[cfg(target_os = "linux")]
+            "subtract" => Key::Raw(82),
+            #[cfg(target_os = "linux")]
+            "sysreq" => Key::SysReq,
+            "tab" => Key::Tab,
+            #[cfg(target_os = "linux")]
+            "undo" => Key::Undo,
+            "up" | "uparrow" => Key::UpArrow,
+            "volumedown" => Key::VolumeDown,
+            "volumemute" | "mute" => Key::VolumeMute,
+            "volumeup" => Key::VolumeUp,
+            #[cfg(target_os = "windows")]
+            "xbutton1" => Key::XButton1,
+            #[cfg(target_os = "windows")]
+            "xbutton2" => Key::XButton2,
+            #[cfg(target_os = "windows")]
+            "zoom" => Key::Zoom,
+            any => {
+                if any.chars().count()!=1 {
+                    return None;
+                    }
+
+                Key::Layout(any.chars().next().unwrap())
+                }
+            })
+        }
+    fn modifier_from_str(name: &str) -> Option<Key> {
+        match name {
+            "control" | "ctrl" => Some(Key::Control),
+            "shift" => Some(Key::Shift),
+            "alt" => Some(Key::Alt),
+            "meta" | "super" | "win" | "windows" | "command" | "cmd" => Some(Key::Meta),
+            "capslock" => Some(Key::CapsLock),
+            _ => None,
+            }
+        }
+    fn modifier_or_key_from_str(name: &str) -> Option<Key> {
+        Operation::modifier_from_str(name).or_else(|| Operation::key_from_str(name))
+        }
+    }
+
+#[derive(Clone, Debug)]
+struct ProcessLaunch {
+    program: String,
+    arguments: Vec<String>,
+    working_directory: Option<String>,
+    announce_output: bool,
+    }
+
+/// Verifies authentication responses and remembers failed attempts per address, so the attempt limit survives reconnecting.
+/// An address failing AUTHENTICATION_ATTEMPTS times is locked out, for twice as long with every further failure.
+struct Authenticator {
+    password: String,
+    failures: Mutex<HashMap<IpAddr, AuthenticationFailures>>,
+    }
+impl Authenticator {
+
+    fn new(password: String) -> Authenticator {
+        Authenticator { password, failures: Mutex::new(HashMap::new()) }
+        }
+
+    /// Returns how long the address has to wait before it may try again, if it's locked out.
+    fn lockout(&self, address: IpAddr) -> Option<Duration> {
+        self.lockout_at(address, Instant::now())
+        }
+    fn lockout_at(&self, address: IpAddr, now: Instant) -> Option<Duration> {
+        let failures=self.failures.lock().unwrap_or_else(|error| error.into_inner());
+        let record=failures.get(&address)?;
+
+        if record.count<AUTHENTICATION_ATTEMPTS {
+            return None;
+            }
+
+        let lockout=AUTHENTICATION_LOCKOUT.saturating_mul(2u32.saturating_pow(record.count-AUTHENTICATION_ATTEMPTS))
+        .min(AUTHENTICATION_MAXIMUM_LOCKOUT);
+
+        lockout.checked_sub(now.saturating_duration_since(record.last_failure))
+        .filter(|remaining| !remaining.is_zero())
+        }
+    /// Verifies a response to the challenge, recording the outcome for the address. A success clears its failures.
+    fn verify(&self, address: IpAddr, challenge: &[u8], response: &[u8]) -> bool {
+        let verified=verify_authentication_response(&self.password, challenge, response);
+        let mut failures=self.failures.lock().unwrap_or_else(|error| error.into_inner());
+
+        if verified {
+            failures.remove(&address);
+            return true;
+            }
+
+        Authenticator::forget_old_failures(&mut failures, Instant::now());
+
+        let record=failures.entry(address).or_insert(AuthenticationFailures { count: 0, last_failure: Instant::now() });
+        record.count+=1;
+        record.last_failure=Instant::now();
+
+        false
+        }
+    /// Forgets addresses that haven't failed for AUTHENTICATION_FAILURE_MEMORY, so the failures of scanners passing by don't pile up.
+    fn forget_old_failures(failures: &mut HashMap<IpAddr, AuthenticationFailures>, now: Instant) {
+        failures.retain(|_, record| now.saturating_duration_since(record.last_failure)<AUTHENTICATION_FAILURE_MEMORY);
+        }
+    }
+
+struct AuthenticationFailures {
+    count: u32,
+    last_failure: Instant,
+    }
+
+/// Reasons a client connection ends other than the client closing it.
+#[derive(Debug)]
+enum ConnectionError {
+    Handshake(tungstenite::Error),
+    Receive(tungstenite::Error),
+    Send(tungstenite::Error),
+    IncompatibleClient(String),
+    AuthenticationFailed,
+    LockedOut(Duration),
+    TimedOut,
+    ExecutionStopped,
+    }
+impl std::fmt::Display for ConnectionError {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConnectionError::Handshake(error) => write!(formatter, "WebSocket handshake failed: {}", error),
+            ConnectionError::Receive(error) => write!(formatter, "Unable to receive a message: {}", error),
+            ConnectionError::Send(error) => write!(formatter, "Unable to send a message: {}", error),
+            ConnectionError::IncompatibleClient(reason) => write!(formatter, "Rejected an incompatible client: {}", reason),
+            ConnectionError::AuthenticationFailed => write!(formatter, "Authentication failed {} times", AUTHENTICATION_ATTEMPTS),
+            ConnectionError::LockedOut(remaining) => write!(formatter, "The address is locked out after failing to authenticate for {} more seconds", remaining.as_secs()+1),
+            ConnectionError::TimedOut => write!(formatter, "The client didn't authenticate within {} seconds", AUTHENTICATION_TIMEOUT.as_secs()),
+            ConnectionError::ExecutionStopped => write!(formatter, "The execution thread has stopped"),
+            }
+        }
+    }
+
+#[derive(Clone, Debug)]
+enum MacroStep {
+    Operation(Operation),
+    Delay(Duration),
+    KeyDown(Key),
+    KeyUp(Key),
+    }
+
+/// A step of a queued macro along with the object it belongs to and its sticky modifiers, or the end of the macro.
+enum QueuedMacroStep {
+    Step(i32, MacroStep, bool, bool, bool),
+    End,
+    }
+
+#[derive(Clone, Debug)]
+enum SchemeSwitch {
+    Next,
+    Previous,
+    Id(i32),
+    }
+
+#[derive(Clone, Debug)]
+enum TrackpadModeSwitch {
+    On,
+    Off,
+    Toggle,
+    }
+
+#[derive(Clone, Debug)]
+enum ClientMessage {
+    Gesture(Gesture),
+    Authenticate(Vec<u8>),
+    UploadSettings(String),
+    DownloadSettings,
+    Hello(u8, u32),
+    PointerMotion(i16, i16),
+    }
+impl ClientMessage {
+
+    fn from_bytes(bytes: &Vec<u8>) -> Result<ClientMessage, String> {
+        if bytes.len()==0 {
+            return Err("empty message".to_string());
+            }
+
+        match bytes[0] {
+            0 => { //A gesture
+                if bytes.len()<6 {
+                    return Err(format!("{} bytes is not enough to define a gesture client message.", bytes.len()));
+                    }
+
+                let finger_count=bytes[1] as i32;
                 let modifier_count=bytes[2] as i32;
                 let start_x=(bytes[3] as f32)/100f32;
                 let start_y=(bytes[4] as f32)/100f32;
 
-                let shape=match bytes[5] {
-                    0 => {//Swipe
-                        if bytes.len()<7 {
-                            return Err("Received a swipe gesture without swipe directions".to_string());
-                            }
+                let shape=match bytes[5] {
+                    0 => GestureShape::Swipe(directions_from_bytes(&bytes[6..])?),
+                    1 => {//Tap
+                        GestureShape::Tap
+                        }
+                    2 => GestureShape::Touch,
+                    3 => GestureShape::LongPress,
+                    4 => GestureShape::DoubleTap,
+                    5 => GestureShape::TripleTap,
+                    6 => GestureShape::HoldSwipe(directions_from_bytes(&bytes[6..])?),
+                    7 => GestureShape::ClockwiseCircle,
+                    8 => GestureShape::CounterClockwiseCircle,
+                    9 => GestureShape::PinchIn,
+                    10 => GestureShape::PinchOut,
+                    11 => GestureShape::RotateClockwise,
+                    12 => GestureShape::RotateCounterClockwise,
+                    shape_identifier => return Err(format!("{} is an unknown gesture shape identifier", shape_identifier)),
+                    };
+
+                //Pinches and rotations carry their magnitude as a big endian u16, in percent of scale change or degrees respectively
+                let magnitude=match shape {
+                    GestureShape::PinchIn | GestureShape::PinchOut | GestureShape::RotateClockwise | GestureShape::RotateCounterClockwise => {
+                        if bytes.len()<8 {
+                            return Err("Received a pinch or rotation gesture without magnitude".to_string());
+                            }
+
+                        u16::from_be_bytes([bytes[6], bytes[7]]) as f32
+                        },
+                    _ => 0.0,
+                    };
+
+                let gesture=Gesture::new(finger_count, modifier_count, start_x, start_y, shape, magnitude);
+
+                return Ok(ClientMessage::Gesture(gesture));
+                },
+            1 => { //An authentication response
+                if bytes.len()<2 {
+                    return Err("Received an authentication response without content".to_string());
+                    }
+
+                Ok(ClientMessage::Authenticate(bytes[1..].to_vec()))
+                },
+            2 => { //A settings upload, carrying the settings json
+                match String::from_utf8(bytes[1..].to_vec()) {
+                    Ok(json) => Ok(ClientMessage::UploadSettings(json)),
+                    Err(_) => Err("Received settings that are not valid UTF-8".to_string()),
+                    }
+                },
+            3 => Ok(ClientMessage::DownloadSettings),
+            4 => { //A hello, carrying the client's protocol version and capabilities. Its layout must stay the same across protocol versions
+                if bytes.len()<6 {
+                    return Err(format!("{} bytes is not enough to define a hello client message.", bytes.len()));
+                    }
+
+                let version=bytes[1];
+                let capabilities=u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]);
+
+                Ok(ClientMessage::Hello(version, capabilities))
+                },
+            5 => { //Finger motion in trackpad mode, as big endian horizontal and vertical deltas
+                if bytes.len()<5 {
+                    return Err(format!("{} bytes is not enough to define a pointer motion client message.", bytes.len()));
+                    }
+
+                let x=i16::from_be_bytes([bytes[1], bytes[2]]);
+                let y=i16::from_be_bytes([bytes[3], bytes[4]]);
+
+                Ok(ClientMessage::PointerMotion(x, y))
+                },
+            identifier => return Err(format!("Unknown client message identifier {}.", identifier)),
+            }
+        }
+
+    }
+
+/// Messages sent from the server to its clients.
+/// Every message is encoded as the protocol version, the message identifier and its payload, with strings sent as UTF-8 filling the rest of the message.
+#[derive(Clone, Debug)]
+enum ServerMessage {
+    AuthenticationChallenge(Vec<u8>),
+    AuthenticationResult(bool),
+    Settings(String),
+    /// Carries the warnings about the accepted settings, one per line, empty if there are none.
+    SettingsAccepted(String),
+    SettingsRejected(String),
+    ActionSelected(String),
+    SchemeChanged(String),
+    CommandExecuted(String),
+    Error(String),
+    Hello(u8, u32),
+    IncompatibleClient(String),
+    ProcessOutput(String),
+    TrackpadMode(bool),
+    }
+impl ServerMessage {
+
+    fn identifier(&self) -> u8 {
+        match self {
+            ServerMessage::AuthenticationChallenge(_) => 0,
+            ServerMessage::AuthenticationResult(_) => 1,
+            ServerMessage::Settings(_) => 2,
+            ServerMessage::SettingsAccepted(_) => 3,
+            ServerMessage::SettingsRejected(_) => 4,
+            ServerMessage::ActionSelected(_) => 5,
+            ServerMessage::SchemeChanged(_) => 6,
+            ServerMessage::CommandExecuted(_) => 7,
+            ServerMessage::Error(_) => 8,
+            ServerMessage::Hello(_, _) => 9,
+            ServerMessage::IncompatibleClient(_) => 10,
+            ServerMessage::ProcessOutput(_) => 11,
+            ServerMessage::TrackpadMode(_) => 12,
+            }
+        }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes=vec![PROTOCOL_VERSION, self.identifier()];
+
+        match self {
+            ServerMessage::AuthenticationChallenge(challenge) => bytes.extend_from_slice(challenge),
+            ServerMessage::AuthenticationResult(success) => bytes.push(*success as u8),
+            ServerMessage::TrackpadMode(enabled) => bytes.push(*enabled as u8),
+            ServerMessage::Hello(version, capabilities) => {
+                bytes.push(*version);
+                bytes.extend_from_slice(&capabilities.to_be_bytes());
+                },
+            ServerMessage::Settings(text)
+            | ServerMessage::SettingsAccepted(text)
+            | ServerMessage::SettingsRejected(text)
+            | ServerMessage::ActionSelected(text)
+            | ServerMessage::SchemeChanged(text)
+            | ServerMessage::CommandExecuted(text)
+            | ServerMessage::Error(text)
+            | ServerMessage::IncompatibleClient(text)
+            | ServerMessage::ProcessOutput(text) => bytes.extend_from_slice(text.as_bytes()),
+            }
+
+        bytes
+        }
+
+    }
+
+struct KeyExecutor {
+    last_executed_object_id: i32,
+    ctrl_down: bool,
+    shift_down: bool,
+    alt_down: bool,
+    meta_down: bool,
+    caps_lock_down: bool,
+    enigo: Enigo,
+    }
+impl KeyExecutor {
+
+    fn new() -> KeyExecutor {
+        KeyExecutor { last_executed_object_id: -1, ctrl_down: false, shift_down: false, alt_down: false, meta_down: false, caps_lock_down: false, enigo: Enigo::new() }
+        }
+
+    fn execute(&mut self, object_id: i32, operation: &Operation, sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool) {
+        match operation {
+            Operation::Shortcut(ctrl, shift, alt, meta, caps_lock, key) => {
+                self.enter_object(object_id);
+
+                if *ctrl { self.ctrl_down(); }
+                if *shift { self.shift_down(); }
+                if *alt { self.alt_down(); }
+                if *meta { self.meta_down(); }
+                if *caps_lock { self.caps_lock_down(); }
+
+                self.enigo.key_click(key.clone());
+
+                if !sticky_ctrl { self.ctrl_up(); }
+                if !sticky_shift { self.shift_up(); }
+                if !sticky_alt { self.alt_up(); }
+                self.meta_up();
+                self.caps_lock_up();
+                },
+            Operation::Text(text) => {
+                self.last_executed_object_id=object_id;
+
+                //Held sticky modifiers would turn the typed characters into shortcuts
+                self.release_modifiers();
+
+                self.enigo.key_sequence(text);
+                },
+            //Mouse operations keep the sticky modifiers of their object, so things like ctrl+click are possible
+            Operation::Click(button, count) => {
+                self.enter_object(object_id);
+
+                for _ in 0..*count {
+                    self.enigo.mouse_click(*button);
+                    }
+                },
+            Operation::Scroll(x, y) => {
+                self.enter_object(object_id);
+
+                if *x!=0 { self.enigo.mouse_scroll_x(*x); }
+                if *y!=0 { self.enigo.mouse_scroll_y(*y); }
+                },
+            Operation::MovePointer(x, y) => self.move_pointer(*x, *y),
+            Operation::MovePointerTo(x, y) => self.enigo.mouse_move_to(*x, *y),
+            _ => {},
+            }
+        }
+
+    /// Releases the modifiers held for the previously executed object when a different one gets executed.
+    fn enter_object(&mut self, object_id: i32) {
+        if object_id!=self.last_executed_object_id {
+            self.last_executed_object_id=object_id;
+
+            self.release_modifiers();
+            }
+        }
+
+    fn move_pointer(&mut self, x: i32, y: i32) {
+        self.enigo.mouse_move_relative(x, y);
+        }
+
+    fn key_down(&mut self, key: Key) {
+        self.enigo.key_down(key);
+        }
+    fn key_up(&mut self, key: Key) {
+        self.enigo.key_up(key);
+        }
+
+    fn release_modifiers(&mut self) {
+        self.ctrl_up();
+        self.shift_up();
+        self.alt_up();
+        self.meta_up();
+        self.caps_lock_up();
+        }
+
+    fn ctrl_down(&mut self) {
+        if !self.ctrl_down {
+            self.enigo.key_down(Key::Control);
+            self.ctrl_down=true;
+            }
+        }
+    fn shift_down(&mut self) {
+        if !self.shift_down {
+            self.enigo.key_down(Key::Shift);
+            self.shift_down=true;
+            }
+        }
+    fn alt_down(&mut self) {
+        if !self.alt_down {
+            self.enigo.key_down(Key::Alt);
+            self.alt_down=true;
+            }
+        }
+    fn meta_down(&mut self) {
+        if !self.meta_down {
+            self.enigo.key_down(Key::Meta);
+            self.meta_down=true;
+            }
+        }
+    fn caps_lock_down(&mut self) {
+        if !self.caps_lock_down {
+            self.enigo.key_down(Key::CapsLock);
+            self.caps_lock_down=true;
+            }
+        }
+
+    fn ctrl_up(&mut self) {
+        if self.ctrl_down {
+            self.enigo.key_up(Key::Control);
+            self.ctrl_down=false;
+            }
+        }
+    fn shift_up(&mut self) {
+        if self.shift_down {
+            self.enigo.key_up(Key::Shift);
+            self.shift_down=false;
+            }
+        }
+    fn alt_up(&mut self) {
+        if self.alt_down {
+            self.enigo.key_up(Key::Alt);
+            self.alt_down=false;
+            }
+        }
+    fn meta_up(&mut self) {
+        if self.meta_down {
+            self.enigo.key_up(Key::Meta);
+            self.meta_down=false;
+            }
+        }
+    fn caps_lock_up(&mut self) {
+        if self.caps_lock_down {
+            self.enigo.key_up(Key::CapsLock);
+            self.caps_lock_down=false;
+            }
+        }
+    }
+
+struct Executor {
+    settings: Settings,
+    active_scheme: usize,
+    trackpad_mode: bool,
+    pointer_remainder: (f32, f32),
+    execution_sender: broadcast::Sender<ServerMessage>,
+    key_executor: KeyExecutor,
+    macro_steps: VecDeque<QueuedMacroStep>,
+    macro_resume_time: Option<Instant>,
+    held_keys: Vec<Key>,
+    deferred_messages: VecDeque<ClientMessage>,
+    }
+impl Executor {
+
+    fn new(settings: Settings, execution_sender: broadcast::Sender<ServerMessage>) -> Executor {
+        Executor { settings, active_scheme: 0, trackpad_mode: false, pointer_remainder: (0.0, 0.0), execution_sender, key_executor: KeyExecutor::new(), macro_steps: VecDeque::new(), macro_resume_time: None, held_keys: Vec::new(), deferred_messages: VecDeque::new() }
+        }
+
+    /// Processes a message from a client. Messages arriving while a macro runs are deferred until it finishes, so their keys don't mix with the macro's.
+    fn process_client_message(&mut self, client_message: ClientMessage) {
+        if self.macro_running() {
+            if self.deferred_messages.len()<MAXIMUM_DEFERRED_MESSAGES {
+                self.deferred_messages.push_back(client_message);
+                }
+            else {
+                warn!("Dropped a client message, too many are waiting for a macro to finish");
+                }
+
+            return;
+            }
+
+        match client_message {
+            ClientMessage::Gesture(gesture) => self.process_gesture(&gesture),
+            ClientMessage::PointerMotion(x, y) => self.process_pointer_motion(x, y),
+            _ => {},
+            }
+        }
+
+    /// Swaps in newly loaded settings, staying on the active scheme if it still exists.
+    fn set_settings(&mut self, settings: Settings) {
+        let active_scheme_id=self.settings.schemes.get(self.active_scheme).map(|scheme| scheme.id);
+
+        self.active_scheme=settings.schemes.iter()
+        .position(|scheme| Some(scheme.id)==active_scheme_id)
+        .unwrap_or(0);
+        self.settings=settings;
+
+        self.key_executor.release_modifiers();
+        }
+
+    /// Messages to clients are sent with let _=, since nobody may be connected to hear them.
+    fn process_gesture(&mut self, gesture: &Gesture) {
+        let active_scheme=match self.settings.schemes.get_mut(self.active_scheme) {
+            Some(scheme) => scheme,
+            None => return,
+            };
+
+        //First, check if the gesture is defined in a command binding
+
+        let mut command: Option<Command>=None;
+
+        for binding in &active_scheme.bindings.command_bindings {
+            if binding.matches(gesture) {
+                command=Some(binding.command_instance.clone());
+                break;
+                }
+            }
+
+        if let Some(command)=command {
+            self.execute(command.id, &command.operation, gesture.magnitude, command.sticky_ctrl, command.sticky_shift, command.sticky_alt);
+            let _=self.execution_sender.send(ServerMessage::CommandExecuted(command.name.clone()));
+            return;
+            }
+
+        //If not, check slot bindings
+        //Operations are collected first and executed afterwards, as executing them may switch the active scheme
+
+        let mut operations: Vec<(Action, SlotOperation)>=Vec::new();
+
+        if let Ok((slot, slot_operation))=gesture.try_get_slot_operation(&self.settings.slot_layout) {
+            for binding in &mut active_scheme.bindings.slot_bindings {
+                if binding.slot==slot && binding.finger_count==gesture.finger_count && binding.modifier_count==gesture.modifier_count {
+                    match slot_operation {
+                        SlotOperation::Forward | SlotOperation::Backward => {
+                            if let Some(action)=binding.active_action() {
+                                operations.push((action.clone(), slot_operation.clone()));
+                                }
+                            },
+                        SlotOperation::PreviousAction => {
+                            binding.previous_action();
+                            if let Some(active_action)=binding.active_action() {
+                                let _=self.execution_sender.send(ServerMessage::ActionSelected(active_action.name.clone()));
+                                }
+                            }
+                        SlotOperation::NextAction => {
+                            binding.next_action();
+                            if let Some(active_action)=binding.active_action() {
+                                let _=self.execution_sender.send(ServerMessage::ActionSelected(active_action.name.clone()));
+                                }
+                            }
+                        SlotOperation::DefaultAction => {
+                            binding.default_action();
+                            if let Some(active_action)=binding.active_action() {
+                                let _=self.execution_sender.send(ServerMessage::ActionSelected(active_action.name.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+        for (action, slot_operation) in operations {
+            let operation=match slot_operation {
+                SlotOperation::Backward => &action.backward_operation,
+                _ => &action.forward_operation,
+                };
+
+            self.execute(action.id, operation, gesture.magnitude, action.sticky_ctrl, action.sticky_shift, action.sticky_alt);
+            }
+        }
+
+    /// Executes an operation for a gesture of the given magnitude, which only repeats use.
+    fn execute(&mut self, object_id: i32, operation: &Operation, magnitude: f32, sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool) {
+        let (count, operation)=match operation {
+            Operation::Repeat(step, operation) => (Operation::repeat_count(*step, magnitude), &**operation),
+            operation => (1, operation),
+            };
+
+        for _ in 0..count {
+            match operation {
+                Operation::Macro(steps) => self.queue_macro(object_id, steps, sticky_ctrl, sticky_shift, sticky_alt),
+                //A gesture may execute several operations, the ones following a macro wait for it, so their keys don't mix
+                _ if !self.macro_steps.is_empty() => self.macro_steps.push_back(QueuedMacroStep::Step(object_id, MacroStep::Operation(operation.clone()), sticky_ctrl, sticky_shift, sticky_alt)),
+                _ => self.execute_immediate(object_id, operation, sticky_ctrl, sticky_shift, sticky_alt),
+                }
+            }
+
+        //A macro waiting on its delay continues when the execution thread resumes it, not earlier
+        if !self.macro_running() {
+            self.run_macro_steps();
+            }
+        }
+    /// Executes operations that complete right away, which is everything except macros.
+    fn execute_immediate(&mut self, object_id: i32, operation: &Operation, sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool) {
+        match operation {
+            Operation::SwitchScheme(scheme_switch) => self.switch_scheme(scheme_switch),
+            Operation::SwitchTrackpadMode(trackpad_mode_switch) => self.switch_trackpad_mode(trackpad_mode_switch),
+            Operation::Run(process_launch) => self.run_process(process_launch),
+            _ => self.key_executor.execute(object_id, operation, sticky_ctrl, sticky_shift, sticky_alt),
+            }
+        }
+
+    fn queue_macro(&mut self, object_id: i32, steps: &[MacroStep], sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool) {
+        for step in steps {
+            self.macro_steps.push_back(QueuedMacroStep::Step(object_id, step.clone(), sticky_ctrl, sticky_shift, sticky_alt));
+            }
+
+        self.macro_steps.push_back(QueuedMacroStep::End);
+        }
+    fn macro_running(&self) -> bool {
+        self.macro_resume_time.is_some()
+        }
+    /// Returns when the running macro continues after its current delay. Without one, the time is irrelevant, so it's now.
+    fn macro_resume_time(&self) -> Instant {
+        self.macro_resume_time.unwrap_or_else(Instant::now)
+        }
+    /// Executes queued macro steps until a delay, after which the execution thread resumes the macro, or until there are none left.
+    /// Delays never block the execution thread, so settings reloads and incoming messages are handled meanwhile.
+    fn run_macro_steps(&mut self) {
+        self.macro_resume_time=None;
+
+        while let Some(queued_step)=self.macro_steps.pop_front() {
+            match queued_step {
+                QueuedMacroStep::Step(object_id, step, sticky_ctrl, sticky_shift, sticky_alt) => match step {
+                    MacroStep::Operation(operation) => self.execute_immediate(object_id, &operation, sticky_ctrl, sticky_shift, sticky_alt),
+                    MacroStep::Delay(delay) => {
+                        self.macro_resume_time=Some(Instant::now()+delay);
+                        return;
+                        },
+                    MacroStep::KeyDown(key) => {
+                        self.key_executor.key_down(key);
+                        self.held_keys.push(key);
+                        },
+                    MacroStep::KeyUp(key) => {
+                        self.key_executor.key_up(key);
+                        self.held_keys.retain(|held_key| *held_key!=key);
+                        },
+                    },
+                QueuedMacroStep::End => {
+                    //Keys the macro forgot to release would otherwise stay pressed
+                    for key in std::mem::take(&mut self.held_keys) {
+                        self.key_executor.key_up(key);
+                        }
+                    },
+                }
+            }
+        }
+    /// Continues the running macro after its delay, then processes the messages deferred while it ran, unless one of them starts another macro.
+    fn resume_macro(&mut self) {
+        self.run_macro_steps();
+
+        while !self.macro_running() {
+            match self.deferred_messages.pop_front() {
+                Some(client_message) => self.process_client_message(client_message),
+                None => break,
+                }
+            }
+        }
+
+    /// Launches a process in its own task, so long-running programs don't hold up gesture processing.
+    fn run_process(&self, process_launch: &ProcessLaunch) {
+        let process_launch=process_launch.clone();
+        let execution_sender=self.execution_sender.clone();
+
+        tokio::spawn(async move {
+            let mut command=tokio::process::Command::new(&process_launch.program);
+            command.args(&process_launch.arguments).stdin(Stdio::null());
+
+            if let Some(working_directory)=&process_launch.working_directory {
+                command.current_dir(working_directory);
+                }
+
+            //Only capture the output when it's going to be announced, launched applications may print a lot over their lifetime
+            if !process_launch.announce_output {
+                command.stdout(Stdio::null()).stderr(Stdio::null());
+                }
+
+            let output=match command.output().await {
+                Ok(output) => output,
+                Err(error) => {
+                    let message=format!("Unable to run {}: {}", process_launch.program, error);
+                    error!("{}", message);
+                    let _=execution_sender.send(ServerMessage::Error(message));
+                    return;
+                    },
+                };
+
+            debug!("{} finished with {}", process_launch.program, output.status);
+
+            if process_launch.announce_output {
+                let stdout=String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+                let message=if !stdout.is_empty() {
+                    ServerMessage::ProcessOutput(stdout)
+                    }
+                else if !output.status.success() {
+                    ServerMessage::Error(format!("{} finished with {}", process_launch.program, output.status))
+                    }
+                else {
+                    return;
+                    };
+
+                let _=execution_sender.send(message);
+                }
+            });
+        }
+
+    fn switch_trackpad_mode(&mut self, trackpad_mode_switch: &TrackpadModeSwitch) {
+        self.trackpad_mode=match trackpad_mode_switch {
+            TrackpadModeSwitch::On => true,
+            TrackpadModeSwitch::Off => false,
+            TrackpadModeSwitch::Toggle => !self.trackpad_mode,
+            };
+        self.pointer_remainder=(0.0, 0.0);
+
+        let _=self.execution_sender.send(ServerMessage::TrackpadMode(self.trackpad_mode));
+        }
+
+    /// Turns finger movement streamed by the client into pointer movement while trackpad mode is on.
+    /// Faster movements get amplified by the acceleration, fractions of pixels are carried over to the next motion so slow movements stay smooth.
+    fn process_pointer_motion(&mut self, x: i16, y: i16) {
+        if !self.trackpad_mode {
+            return;
+            }
+
+        let trackpad=&self.settings.trackpad;
+        let (x, y)=(x as f32, y as f32);
+
+        let speed=(x*x+y*y).sqrt();
+        let factor=trackpad.sensitivity*(1.0+trackpad.acceleration*speed);
+
+        let pointer_x=x*factor+self.pointer_remainder.0;
+        let pointer_y=y*factor+self.pointer_remainder.1;
+
+        self.pointer_remainder=(pointer_x.fract(), pointer_y.fract());
+
+        let (pointer_x, pointer_y)=(pointer_x.trunc() as i32, pointer_y.trunc() as i32);
+
+        if pointer_x!=0 || pointer_y!=0 {
+            self.key_executor.move_pointer(pointer_x, pointer_y);
+            }
+        }
+
+    fn switch_scheme(&mut self, scheme_switch: &SchemeSwitch) {
+        let scheme_count=self.settings.schemes.len();
+
+        if scheme_count==0 {
+            return;
+            }
+
+        self.active_scheme=match scheme_switch {
+            SchemeSwitch::Next => (self.active_scheme+1)%scheme_count,
+            SchemeSwitch::Previous => (self.active_scheme+scheme_count-1)%scheme_count,
+            SchemeSwitch::Id(id) => match self.settings.schemes.iter().position(|scheme| scheme.id==*id) {
+                Some(index) => index,
+                None => return,
+                },
+            };
+
+        //Modifiers held by sticky operations of the previous scheme shouldn't leak into the new one
+        self.key_executor.release_modifiers();
+
+        let _=self.execution_sender.send(ServerMessage::SchemeChanged(self.settings.schemes[self.active_scheme].name.clone()));
+        }
+
+    }
+
+#[tokio::main]
+async fn main() {
+    let arguments=Arguments::parse();
+
+    log::set_logger(&LOGGER).expect("No other logger is set");
+    log::set_max_level(arguments.log_level());
+
+    if arguments.print_default_settings {
+        println!("{}", serde_json::to_string_pretty(&Settings::starter()).expect("Settings are always serializable"));
+        return;
+        }
+    if arguments.check {
+        std::process::exit(check_settings(&arguments.settings));
+        }
+
+    let (communication_sender, communication_receiver)=mpsc::channel::<ClientMessage>(10);
+    let (execution_sender, _)=broadcast::channel::<ServerMessage>(10);
+
+    let settings=match load_settings(&arguments.settings) {
+        Ok(settings) => settings,
+        Err(error) => {
+            error!("Unable to load settings from {}: {}", arguments.settings, error);
+            return;
+            },
+        };
+    let (settings_sender, settings_receiver)=watch::channel(settings);
+    let settings_sender=Arc::new(settings_sender);
+
+    tokio::spawn(execution_thread(communication_receiver, settings_receiver, execution_sender.clone()));
+    tokio::spawn(settings_watcher_thread(arguments.settings.clone(), settings_sender.clone(), execution_sender.clone()));
+    #[cfg(unix)]
+    tokio::spawn(settings_reload_signal_thread(arguments.settings.clone(), settings_sender.clone(), execution_sender.clone()));
+
+    let password=match get_password(&arguments) {
+        Ok(password) => password,
+        Err(error) => {
+            error!("{}", error);
+            return;
+            },
+        };
+    let authenticator=Arc::new(Authenticator::new(password));
+
+    let tls_acceptor=match get_tls_acceptor(&arguments) {
+        Ok(tls_acceptor) => tls_acceptor,
+        Err(error) => {
+            error!("Unable to set up TLS: {}", error);
+            return;
+            },
+        };
+
+    let addresses=match get_host(&arguments) {
+        Ok(addresses) => addresses,
+        Err(error) => {
+            error!("{}", error);
+            return;
+            },
+        };
+
+    let mut listeners: Vec<tokio::task::JoinHandle<()>>=Vec::new();
+
+    //Clients on the network can only reach listeners on non-loopback addresses, preferably IPv4 ones, as discovery requests are IPv4 broadcasts
+    let discoverable_address=addresses.iter()
+    .filter(|address| !address.ip().is_loopback())
+    .min_by_key(|address| !address.is_ipv4());
+
+    if !arguments.no_discovery {
+        match discoverable_address {
+            Some(discoverable_address) => {
+                let name=arguments.name.clone().unwrap_or_else(|| gethostname::gethostname().to_string_lossy().to_string());
+                let response=discovery_response(&name, discoverable_address.port(), tls_acceptor.is_some());
+
+                match UdpSocket::bind((DEFAULT_HOST, arguments.discovery_port)).await {
+                    Ok(socket) => {
+                        info!("Answering discovery requests on port {} as {}", arguments.discovery_port, name);
+                        tokio::spawn(discovery_thread(socket, response));
+                        },
+                    //The server is still usable without discovery, the address just has to be entered manually
+                    Err(error) => warn!("Unable to answer discovery requests on port {}: {}", arguments.discovery_port, error),
+                    }
+                },
+            None => info!("Not answering discovery requests, the server only listens on loopback addresses"),
+            }
+        }
+
+    for address in addresses {
+        let server=match TcpListener::bind(address).await {
+            Ok(server) => server,
+            Err(error) => {
+                error!("Unable to listen on {}: {}", address, error);
+                return;
+                },
+            };
+
+        info!("Listening on {}", address);
+
+        listeners.push(tokio::spawn(listener_thread(server, tls_acceptor.clone(), authenticator.clone(), arguments.settings.clone(), communication_sender.clone(), settings_sender.clone(), execution_sender.clone())));
+        }
+
+    match local_ip_address::local_ip() {
+        Ok(local_ip) => info!("Launched server on {:?}", local_ip),
+        Err(error) => info!("Launched server, unable to determine the local IP address: {}", error),
+        }
+
+    futures_util::future::join_all(listeners).await;
+    }
+
+async fn listener_thread(server: TcpListener, tls_acceptor: Option<TlsAcceptor>, authenticator: Arc<Authenticator>, settings_path: String, communication_sender: mpsc::Sender<ClientMessage>, settings_sender: Arc<watch::Sender<Settings>>, execution_sender: broadcast::Sender<ServerMessage>) {
+    loop {
+        let (stream, address)=match server.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                //Accepting fails for reasons like running out of file descriptors, which may pass, so keep listening after a short pause
+                warn!("Unable to accept a connection: {}", error);
+                tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+                },
+            };
+
+        debug!("Incoming stream");
+        let execution_receiver=execution_sender.subscribe();
+
+        match &tls_acceptor {
+            Some(tls_acceptor) => {
+                tokio::spawn(tls_communication_thread(tls_acceptor.clone(), stream, authenticator.clone(), settings_path.clone(), communication_sender.clone(), settings_sender.clone(), execution_receiver));
+                },
+            None => {
+                tokio::spawn(communication_thread(stream, address.ip(), authenticator.clone(), settings_path.clone(), communication_sender.clone(), settings_sender.clone(), execution_receiver));
+                },
+            }
+        }
+    }
+
+/// Answers discovery requests clients broadcast over the local network, so they can list servers without knowing their addresses.
+async fn discovery_thread(socket: UdpSocket, response: Vec<u8>) {
+    let mut buffer=[0u8; 64];
+
+    loop {
+        let (length, client_address)=match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(error) => {
+                warn!("Unable to receive a discovery request: {}", error);
+                continue;
+                },
+            };
+
+        if &buffer[..length]!=DISCOVERY_REQUEST {
+            continue;
+            }
+
+        debug!("Discovery request from {}", client_address);
+
+        if let Err(error)=socket.send_to(&response, client_address).await {
+            warn!("Unable to answer the discovery request from {}: {}", client_address, error);
+            }
+        }
+    }
+
+async fn tls_communication_thread(tls_acceptor: TlsAcceptor, stream: TcpStream, authenticator: Arc<Authenticator>, settings_path: String, communication_sender: mpsc::Sender<ClientMessage>, settings_sender: Arc<watch::Sender<Settings>>, execution_receiver: broadcast::Receiver<ServerMessage>) {
+    let address=match stream.peer_addr() {
+        Ok(address) => address.ip(),
+        Err(error) => {
+            warn!("Unable to determine the address of a connection: {}", error);
+            return;
+            },
+        };
+
+    match tokio::time::timeout(AUTHENTICATION_TIMEOUT, tls_acceptor.accept(stream)).await {
+        Ok(Ok(tls_stream)) => communication_thread(tls_stream, address, authenticator, settings_path, communication_sender, settings_sender, execution_receiver).await,
+        Ok(Err(error)) => warn!("TLS handshake failed: {}", error),
+        Err(_) => warn!("TLS handshake with {} timed out", address),
+        }
+    }
+async fn communication_thread<S: AsyncRead+AsyncWrite+Unpin>(stream: S, address: IpAddr, authenticator: Arc<Authenticator>, settings_path: String, communication_sender: mpsc::Sender<ClientMessage>, settings_sender: Arc<watch::Sender<Settings>>, execution_receiver: broadcast::Receiver<ServerMessage>) {
+    match handle_connection(stream, address, authenticator, settings_path, communication_sender, settings_sender, execution_receiver).await {
+        Ok(()) => info!("A connection closed"),
+        Err(error) => warn!("A connection closed: {}", error),
+        }
+    }
+/// Serves a single client, returning when the connection is closed. Nothing the client sends can end it with anything worse than an error.
+async fn handle_connection<S: AsyncRead+AsyncWrite+Unpin>(stream: S, address: IpAddr, authenticator: Arc<Authenticator>, settings_path: String, communication_sender: mpsc::Sender<ClientMessage>, settings_sender: Arc<watch::Sender<Settings>>, mut execution_receiver: broadcast::Receiver<ServerMessage>) -> Result<(), ConnectionError> {
+    //A client that stays silent would otherwise hold on to its task and file descriptor forever
+    let deadline=tokio::time::Instant::now()+AUTHENTICATION_TIMEOUT;
+
+    let ws_stream=tokio::time::timeout_at(deadline, tokio_tungstenite::accept_async(stream)).await
+    .map_err(|_| ConnectionError::TimedOut)?
+    .map_err(ConnectionError::Handshake)?;
+    let (mut ws_sender, mut ws_receiver)=ws_stream.split();
+
+    info!("New connection established");
+
+    //Before anything else, the client has to introduce itself, so incompatible clients can be turned away with a clear reason
+    let mut hello=Err("The connection closed before a hello message was received".to_string());
+
+    loop {
+        let msg=match tokio::time::timeout_at(deadline, ws_receiver.next()).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(_) => {
+                let _=ws_sender.close().await;
+                return Err(ConnectionError::TimedOut);
+                },
+            };
+
+        hello=match msg.map_err(ConnectionError::Receive)? {
+            Message::Binary(data) => ClientMessage::from_bytes(&data),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            _ => Err("Expected a binary message".to_string()),
+            };
+        break;
+        }
+
+    match negotiate_protocol(hello) {
+        Ok((version, capabilities)) => {
+            debug!("Negotiated protocol version {} with client capabilities {:#x}", version, capabilities);
+            send_server_message(&mut ws_sender, ServerMessage::Hello(version, SERVER_CAPABILITIES)).await?;
+            },
+        Err(reason) => {
+            //The client may already be gone
+            let _=send_server_message(&mut ws_sender, ServerMessage::IncompatibleClient(reason.clone())).await;
+            let _=ws_sender.close().await;
+            return Err(ConnectionError::IncompatibleClient(reason));
+            },
+        }
+
+    let mut authenticated=false;
+    let mut failed_attempts=0;
+
+    let mut challenge=generate_authentication_challenge();
+    send_server_message(&mut ws_sender, ServerMessage::AuthenticationChallenge(challenge.clone())).await?;
+
+    loop {
+        tokio::select! {
+            msg = ws_receiver.next() => {
+                let data=match msg {
+                    Some(msg) => match msg.map_err(ConnectionError::Receive)? {
+                        Message::Binary(data) => data,
+                        Message::Close(_) => break,
+                        _ => continue,
+                        },
+                    None => break,
+                    };
+
+                match ClientMessage::from_bytes(&data) {
+                    Ok(ClientMessage::Authenticate(response)) => {
+                        if authenticated { continue; }
+
+                        if let Some(remaining)=authenticator.lockout(address) {
+                            let _=send_server_message(&mut ws_sender, ServerMessage::AuthenticationResult(false)).await;
+                            let _=ws_sender.close().await;
+                            return Err(ConnectionError::LockedOut(remaining));
+                            }
+
+                        if authenticator.verify(address, &challenge, &response) {
+                            authenticated=true;
+                            send_server_message(&mut ws_sender, ServerMessage::AuthenticationResult(true)).await?;
+                            info!("Authenticated!");
+                            continue;
+                            }
+
+                        failed_attempts+=1;
+                        warn!("Authentication from {} failed ({}/{})", address, failed_attempts, AUTHENTICATION_ATTEMPTS);
+                        send_server_message(&mut ws_sender, ServerMessage::AuthenticationResult(false)).await?;
+
+                        if failed_attempts>=AUTHENTICATION_ATTEMPTS {
+                            let _=ws_sender.close().await;
+                            return Err(ConnectionError::AuthenticationFailed);
+                            }
+
+                        //Every attempt gets a fresh challenge, so a captured response can't be replayed
+                        challenge=generate_authentication_challenge();
+                        send_server_message(&mut ws_sender, ServerMessage::AuthenticationChallenge(challenge.clone())).await?;
+                        },
+                    Ok(ClientMessage::UploadSettings(json)) => {
+                        if !authenticated { continue; }
+
+                        let response=match Settings::from_json_with_warnings(&json) {
+                            Ok((settings, warnings)) => {
+                                settings_sender.send_replace(settings);
+                                info!("Received new settings");
+
+                                if let Err(error)=save_settings(&settings_path, &json) {
+                                    error!("Unable to save the received settings to {}: {}", settings_path, error);
+                                    }
+
+                                let warnings: Vec<String>=warnings.iter()
+                                .map(|warning| warning.to_string())
+                                .collect();
+
+                                ServerMessage::SettingsAccepted(warnings.join("\n"))
+                                },
+                            Err(error) => ServerMessage::SettingsRejected(error),
+                            };
+
+                        send_server_message(&mut ws_sender, response).await?;
+                        },
+                    Ok(ClientMessage::DownloadSettings) => {
+                        if !authenticated { continue; }
+
+                        let response=match serde_json::to_string(&*settings_sender.borrow()) {
+                            Ok(json) => ServerMessage::Settings(json),
+                            Err(error) => ServerMessage::Error(format!("Unable to serialize the settings: {}", error)),
+                            };
+
+                        send_server_message(&mut ws_sender, response).await?;
+                        },
+                    Ok(client_message) => {
+                        if !authenticated { continue; }
+
+                        communication_sender.send(client_message).await
+                        .map_err(|_| ConnectionError::ExecutionStopped)?;
+                        },
+                    Err(error) => {
+                        if !authenticated { continue; }
+
+                        send_server_message(&mut ws_sender, ServerMessage::Error(error)).await?;
+                        },
+                    }
+                }
+            msg = execution_receiver.recv() => {
+                match msg {
+                    Ok(msg) => {
+                        if !authenticated { continue; }
+
+                        send_server_message(&mut ws_sender, msg).await?;
+                        },
+                    //A slow client misses some announcements, which isn't worth dropping it for
+                    Err(broadcast::error::RecvError::Lagged(count)) => warn!("A client missed {} messages", count),
+                    Err(broadcast::error::RecvError::Closed) => return Err(ConnectionError::ExecutionStopped),
+                    }
+                }
+            _ = tokio::time::sleep_until(deadline), if !authenticated => {
+                let _=ws_sender.close().await;
+                return Err(ConnectionError::TimedOut);
+                }
+            }
+        }
+
+    Ok(())
+    }
+async fn send_server_message<W: Sink<Message, Error=tungstenite::Error>+Unpin>(ws_sender: &mut W, message: ServerMessage) -> Result<(), ConnectionError> {
+    ws_sender.send(Message::Binary(message.to_bytes())).await
+    .map_err(ConnectionError::Send)
+    }
+async fn execution_thread(mut communication_receiver: mpsc::Receiver<ClientMessage>, mut settings_receiver: watch::Receiver<Settings>, execution_sender: broadcast::Sender<ServerMessage>) {
+    let settings=settings_receiver.borrow_and_update().clone();
+
+    let mut executor=Executor::new(settings, execution_sender);
+
+    loop {
+        tokio::select! {
+            client_message = communication_receiver.recv() => {
+                match client_message {
+                    Some(client_message) => executor.process_client_message(client_message),
+                    None => break,
+                    }
+                }
+            Ok(()) = settings_receiver.changed() => {
+                let settings=settings_receiver.borrow_and_update().clone();
+                executor.set_settings(settings);
+                }
+            _ = tokio::time::sleep_until(executor.macro_resume_time().into()), if executor.macro_running() => executor.resume_macro(),
+            }
+        }
+    }
+/// Polls the settings file for modifications and swaps the reloaded settings in. A file that fails to parse or finalize is reported and the previous settings stay active.
+async fn settings_watcher_thread(path: String, settings_sender: Arc<watch::Sender<Settings>>, execution_sender: broadcast::Sender<ServerMessage>) {
+    let mut last_modified=settings_modification_time(&path);
+    let mut interval=tokio::time::interval(SETTINGS_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified=settings_modification_time(&path);
+
+        if modified.is_none() || modified==last_modified {
+            continue;
+            }
+
+        last_modified=modified;
+        reload_settings(&path, &settings_sender, &execution_sender);
+        }
+    }
+/// Reloads the settings on SIGHUP, for setups where the file's modification time isn't reliable.
+#[cfg(unix)]
+async fn settings_reload_signal_thread(path: String, settings_sender: Arc<watch::Sender<Settings>>, execution_sender: broadcast::Sender<ServerMessage>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup=match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(error) => {
+            error!("Unable to listen for SIGHUP: {}", error);
+            return;
+            },
+        };
+
+    while hangup.recv().await.is_some() {
+        reload_settings(&path, &settings_sender, &execution_sender);
+        }
+    }
+
+/// Saves settings by replacing the file with a completely written one, so the settings watcher never reads a partially written file.
+fn save_settings(path: &str, json: &str) -> std::io::Result<()> {
+    let temporary_path=format!("{}.tmp", path);
+
+    fs::write(&temporary_path, json)?;
+    fs::rename(&temporary_path, path)
+    }
+
+fn load_settings(path: &str) -> Result<Settings, String> {
+    fs::read_to_string(path)
+    .map_err(|error| error.to_string())
+    .and_then(|json| Settings::from_json(&json))
+    }
+/// Validates the settings file at the given path for the --check mode, printing the result and returning the process exit code.
+fn check_settings(path: &str) -> i32 {
+    match load_settings(path) {
+        Ok(_) => {
+            println!("{} is valid", path);
+            0
+            },
+        Err(error) => {
+            println!("{}: {}", path, error);
+            1
+            },
+        }
+    }
+fn reload_settings(path: &str, settings_sender: &watch::Sender<Settings>, execution_sender: &broadcast::Sender<ServerMessage>) {
+    match load_settings(path) {
+        Ok(settings) => {
+            //Skip files whose content matches the active settings, such as the ones saved after an upload from a client
+            if serde_json::to_string(&settings).ok()==serde_json::to_string(&*settings_sender.borrow()).ok() {
+                return;
+                }
+
+            settings_sender.send_replace(settings);
+            info!("Reloaded settings from {}", path);
+            },
+        Err(error) => {
+            warn!("Unable to reload settings from {}, keeping the previous ones: {}", path, error);
+
+            //Nobody may be connected to hear about it, which is fine
+            let _=execution_sender.send(ServerMessage::SettingsRejected(error));
+            },
+        }
+    }
+fn settings_modification_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+
+/// Builds the answer to discovery requests: the response header, protocol version, flags, port as a big-endian u16 and the server name in UTF-8.
+/// Clients connect to the address the answer came from.
+fn discovery_response(name: &str, port: u16, tls: bool) -> Vec<u8> {
+    let mut response=DISCOVERY_RESPONSE_HEADER.to_vec();
+
+    response.push(PROTOCOL_VERSION);
+    response.push(if tls { DISCOVERY_FLAG_TLS } else { 0 });
+    response.extend_from_slice(&port.to_be_bytes());
+    response.extend_from_slice(name.as_bytes());
+
+    response
+    }
+
+/// Checks the client's hello and returns the protocol version both sides speak, along with the client's capabilities.
+fn negotiate_protocol(hello: Result<ClientMessage, String>) -> Result<(u8, u32), String> {
+    match hello {
+        Ok(ClientMessage::Hello(version, capabilities)) => {
+            let version=version.min(PROTOCOL_VERSION);
+
+            if version<MINIMUM_PROTOCOL_VERSION {
+                return Err(format!("The client speaks protocol version {}, but the server requires at least version {}. Please update the client.", version, MINIMUM_PROTOCOL_VERSION));
+                }
+
+            Ok((version, capabilities))
+            },
+        Ok(_) => Err("The client did not start with a hello message. It is likely too old for this server, please update it.".to_string()),
+        Err(error) => Err(format!("Invalid hello message: {}", error)),
+        }
+    }
+
+/// Parses the directions of a swipe or hold-then-swipe gesture, one byte each.
+fn directions_from_bytes(bytes: &[u8]) -> Result<Vec<Direction>, String> {
+    if bytes.is_empty() {
+        return Err("Received a swipe gesture without swipe directions".to_string());
+        }
+
+    let mut swipe_directions: Vec<Direction>=Vec::new();
+
+    for byte in bytes {
+        swipe_directions.push(match byte {
+            0 => Direction::Left,
+            1 => Direction::Right,
+            2 => Direction::Up,
+            3 => Direction::Down,
+            4 => Direction::UpLeft,
+            5 => Direction::UpRight,
+            6 => Direction::DownLeft,
+            7 => Direction::DownRight,
+            direction => return Err(format!("Invalid swipe direction {}", direction)),
+            });
+        }
+
+    Ok(swipe_directions)
+    }
+
+/// Resolves the addresses to listen on, reporting the first one that can't be parsed or resolved.
+fn get_host(arguments: &Arguments) -> Result<Vec<SocketAddr>, String> {
+    if arguments.host.is_empty() {
+        return Ok(vec![SocketAddr::new(DEFAULT_HOST.parse().expect("The default host is a valid IP address"), arguments.port)]);
+        }
+
+    let mut addresses: Vec<SocketAddr>=Vec::new();
+
+    for host in &arguments.host {
+        for address in parse_host(host.trim(), arguments.port)? {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+                }
+            }
+        }
+
+    Ok(addresses)
+    }
+/// Parses a single address to listen on, which may be a ws:// or wss:// URL, an IP address or host name, each with or without a port.
+fn parse_host(host: &str, default_port: u16) -> Result<Vec<SocketAddr>, String> {
+    if host.is_empty() {
+        return Err("Invalid address to listen on: the address is empty".to_string());
+        }
+
+    if host.contains("://") {
+        let url=Url::parse(host)
+        .map_err(|error| format!("Invalid address to listen on \"{}\": {}", host, error))?;
+        let port=url.port().unwrap_or(default_port);
+
+        return match url.host() {
+            Some(url::Host::Ipv4(address)) => Ok(vec![SocketAddr::new(IpAddr::V4(address), port)]),
+            Some(url::Host::Ipv6(address)) => Ok(vec![SocketAddr::new(IpAddr::V6(address), port)]),
+            Some(url::Host::Domain(domain)) => resolve_host(host, (domain, port)),
+            None => Err(format!("Invalid address to listen on \"{}\": the URL has no host", host)),
+            };
+        }
+
+    if let Ok(address)=host.parse::<SocketAddr>() {
+        return Ok(vec![address]);
+        }
+    if let Ok(address)=host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(address, default_port)]);
+        }
+
+    //What remains is a host name, with a port if it has a colon
+    match host.rsplit_once(':') {
+        Some((name, port)) => {
+            let port=port.parse::<u16>()
+            .map_err(|_| format!("Invalid address to listen on \"{}\": \"{}\" is not a valid port", host, port))?;
+
+            resolve_host(host, (name, port))
+            },
+        None => resolve_host(host, (host, default_port)),
+        }
+    }
+fn resolve_host(host: &str, address: (&str, u16)) -> Result<Vec<SocketAddr>, String> {
+    let addresses: Vec<SocketAddr>=address.to_socket_addrs()
+    .map_err(|error| format!("Unable to resolve the address to listen on \"{}\": {}", host, error))?
+    .collect();
+
+    if addresses.is_empty() {
+        return Err(format!("Unable to resolve the address to listen on \"{}\": the name has no addresses", host));
+        }
+
+    Ok(addresses)
+    }
 
-                        let mut swipe_directions: Vec<Direction>=Vec::new();
+fn get_tls_acceptor(arguments: &Arguments) -> Result<Option<TlsAcceptor>, String> {
+    //Clap makes sure the certificate and key are given together and not along with a self-signed certificate
+    let (certificate_path, key_path)=match (&arguments.tls_certificate, &arguments.tls_key) {
+        (Some(certificate_path), Some(key_path)) => (certificate_path.clone(), key_path.clone()),
+        _ => {
+            if !arguments.tls_self_signed {
+                return Ok(None);
+                }
 
-                        for byte in &bytes[6..] {
-                            swipe_directions.push(match byte {
-                                0 => Direction::Left,
-                                1 => Direction::Right,
-                                2 => Direction::Up,
-                                3 => Direction::Down,
-                                direction => return Err(format!("Invalid swipe direction {}", direction)),
-                                });
-                            }
+            let directory=std::path::Path::new(&arguments.settings).parent().unwrap_or(std::path::Path::new(""));
+            let certificate_path=directory.join(SELF_SIGNED_CERTIFICATE_FILE).to_string_lossy().to_string();
+            let key_path=directory.join(SELF_SIGNED_KEY_FILE).to_string_lossy().to_string();
 
-                        GestureShape::Swipe(swipe_directions)
-                        },
-                    1 => {//Tap
-                        GestureShape::Tap
-                        }
-                    2 => GestureShape::Touch,
-                    shape_identifier => return Err(format!("{} is an unknown gesture shape identifier", shape_identifier)),
-                    };
+            ensure_self_signed_certificate(&certificate_path, &key_path)?;
 
-                let gesture=Gesture::new(finger_count, modifier_count, start_x, start_y, shape);
+            (certificate_path, key_path)
+            },
+        };
 
-                return Ok(ClientMessage::Gesture(gesture));
-                },
-            identifier => return Err(format!("Unknown client message identifier {}.", identifier)),
-            }
-        }
+    let certificates=load_certificates(&certificate_path)?;
+    let key=load_private_key(&key_path)?;
+
+    //Printed regardless of the log level, so clients can be checked against it
+    println!("TLS certificate fingerprint (SHA-256): {}", certificate_fingerprint(&certificates[0]));
 
+    let config=ServerConfig::builder()
+    .with_safe_defaults()
+    .with_no_client_auth()
+    .with_single_cert(certificates, key)
+    .map_err(|error| format!("Invalid TLS certificate or key: {}", error))?;
+
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
     }
 
-struct KeyExecutor {
-    last_executed_object_id: i32,
-    ctrl_down: bool,
-    shift_down: bool,
-    alt_down: bool,
-    meta_down: bool,
-    caps_lock_down: bool,
-    enigo: Enigo,
+/// Generates a self-signed certificate on the first launch and reuses it afterwards, so its fingerprint stays stable for pinning in clients.
+fn ensure_self_signed_certificate(certificate_path: &str, key_path: &str) -> Result<(), String> {
+    if fs::metadata(certificate_path).is_ok() && fs::metadata(key_path).is_ok() {
+        return Ok(());
+        }
+
+    let mut subject_alt_names=vec!["localhost".to_string()];
+    if let Ok(local_ip)=local_ip_address::local_ip() {
+        subject_alt_names.push(local_ip.to_string());
+        }
+
+    let certificate=rcgen::generate_simple_self_signed(subject_alt_names)
+    .map_err(|error| format!("Unable to generate a self-signed certificate: {}", error))?;
+
+    let certificate_pem=certificate.serialize_pem()
+    .map_err(|error| format!("Unable to serialize the self-signed certificate: {}", error))?;
+
+    fs::write(certificate_path, certificate_pem)
+    .map_err(|error| format!("Unable to write {}: {}", certificate_path, error))?;
+    write_private_key(key_path, &certificate.serialize_private_key_pem())
+    .map_err(|error| format!("Unable to write {}: {}", key_path, error))?;
+
+    info!("Generated a self-signed certificate in {}", certificate_path);
+
+    Ok(())
     }
-impl KeyExecutor {
 
-    fn new() -> KeyExecutor {
-        KeyExecutor { last_executed_object_id: -1, ctrl_down: false, shift_down: false, alt_down: false, meta_down: false, caps_lock_down: false, enigo: Enigo::new() }
+/// Writes a private key so that only the current user can read it.
+fn write_private_key(path: &str, key: &str) -> std::io::Result<()> {
+    let mut options=fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
         }
 
-    fn execute(&mut self, object_id: i32, operation: &Operation, sticky_ctrl: bool, sticky_shift: bool, sticky_alt: bool) {
-        if let Operation::Shortcut(ctrl, shift, alt, meta, caps_lock, key)=operation {
-            if object_id!=self.last_executed_object_id {
-                self.last_executed_object_id=object_id;
+    let mut file=options.open(path)?;
 
-                self.release_modifiers();
-                }
+    //The mode only applies to newly created files, a leftover key may still be readable by others
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+
+    file.write_all(key.as_bytes())
+    }
+
+fn load_certificates(path: &str) -> Result<Vec<Certificate>, String> {
+    let file=fs::File::open(path).map_err(|error| format!("Unable to open {}: {}", path, error))?;
+
+    let certificates=rustls_pemfile::certs(&mut BufReader::new(file))
+    .map_err(|error| format!("Unable to read certificates from {}: {}", path, error))?;
 
-            if *ctrl { self.ctrl_down(); }
-            if *shift { self.shift_down(); }
-            if *alt { self.alt_down(); }
-            if *meta { self.meta_down(); }
-            if *caps_lock { self.caps_lock_down(); }
+    if certificates.is_empty() {
+        return Err(format!("{} contains no certificates", path));
+        }
+
+    Ok(certificates.into_iter().map(Certificate).collect())
+    }
+fn load_private_key(path: &str) -> Result<PrivateKey, String> {
+    let file=fs::File::open(path).map_err(|error| format!("Unable to open {}: {}", path, error))?;
 
-            self.enigo.key_click(key.clone());
+    let items=rustls_pemfile::read_all(&mut BufReader::new(file))
+    .map_err(|error| format!("Unable to read private key from {}: {}", path, error))?;
 
-            if !sticky_ctrl { self.ctrl_up(); }
-            if !sticky_shift { self.shift_up(); }
-            if !sticky_alt { self.alt_up(); }
-            self.meta_up();
-            self.caps_lock_up();
+    for item in items {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {},
             }
         }
 
-    fn release_modifiers(&mut self) {
-        self.ctrl_up();
-        self.shift_up();
-        self.alt_up();
-        self.meta_up();
-        self.caps_lock_up();
+    Err(format!("{} contains no private key", path))
+    }
+
+fn certificate_fingerprint(certificate: &Certificate) -> String {
+    use sha2::Digest;
+
+    Sha256::digest(&certificate.0).iter()
+    .map(|byte| format!("{:02X}", byte))
+    .collect::<Vec<String>>()
+    .join(":")
+    }
+
+fn get_password(arguments: &Arguments) -> Result<String, String> {
+    //An explicitly given password must not be empty, silently falling back to a pairing code would hide the mistake
+    match &arguments.password_file {
+        Some(path) => {
+            let content=fs::read_to_string(path)
+            .map_err(|error| format!("Unable to read the password from {}: {}", path, error))?;
+
+            let password=content.lines().next().unwrap_or("");
+            if password.trim().is_empty() {
+                return Err(format!("The first line of {} is empty, it must contain the password", path));
+                }
+
+            return Ok(password.to_string());
+            },
+        None => if let Some(password)=&arguments.password {
+            if password.trim().is_empty() {
+                return Err("The password is empty, omit it to use a pairing code instead".to_string());
+                }
+
+            return Ok(password.clone());
+            },
         }
 
-    fn ctrl_down(&mut self) {
-        if !self.ctrl_down {
-            self.enigo.key_down(Key::Control);
-            self.ctrl_down=true;
+    //Without a configured password, generate a pairing code to be typed into the client
+    //It's printed regardless of the log level, since clients can't connect without it
+    let mut rng=rand::thread_rng();
+    let pairing_code: String=(0..PAIRING_CODE_LENGTH)
+    .map(|_| PAIRING_CODE_ALPHABET[rng.gen_range(0..PAIRING_CODE_ALPHABET.len())] as char)
+    .collect();
+    println!("Pairing code: {}", pairing_code);
+
+    Ok(pairing_code)
+    }
+
+fn generate_authentication_challenge() -> Vec<u8> {
+    let mut challenge=vec![0u8; AUTHENTICATION_CHALLENGE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut challenge);
+
+    challenge
+    }
+/// The client proves knowledge of the password by returning HMAC-SHA256 of the challenge keyed with the password, so the password itself never crosses the wire.
+fn verify_authentication_response(password: &str, challenge: &[u8], response: &[u8]) -> bool {
+    let mut mac=HmacSha256::new_from_slice(password.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(challenge);
+
+    mac.verify_slice(response).is_ok()
+    }
+
+fn split_macro_steps(input: &str) -> Vec<String> {
+    let mut steps: Vec<String>=Vec::new();
+    let mut step=String::new();
+    let mut chars=input.chars();
+
+    while let Some(character)=chars.next() {
+        match character {
+            '\\' => {
+                if let Some(escaped)=chars.next() {
+                    step.push(escaped);
+                    }
+                },
+            ';' => steps.push(std::mem::take(&mut step)),
+            character => step.push(character),
             }
         }
-    fn shift_down(&mut self) {
-        if !self.shift_down {
-            self.enigo.key_down(Key::Shift);
-            self.shift_down=true;
+    steps.push(step);
+
+    steps
+    }
+
+/// Splits a command line into arguments on whitespace. Single or double quotes group an argument, a backslash takes the next character literally.
+/// Returns None for unterminated quotes.
+fn split_command_line(input: &str) -> Option<Vec<String>> {
+    let mut arguments: Vec<String>=Vec::new();
+    let mut argument: Option<String>=None;
+    let mut quote: Option<char>=None;
+    let mut chars=input.chars();
+
+    while let Some(character)=chars.next() {
+        match (character, quote) {
+            ('\\', _) => argument.get_or_insert_with(String::new).push(chars.next()?),
+            (character, Some(quote_character)) if character==quote_character => quote=None,
+            (character, Some(_)) => argument.get_or_insert_with(String::new).push(character),
+            ('"' | '\'', None) => {
+                quote=Some(character);
+                argument.get_or_insert_with(String::new);
+                },
+            (character, None) if character.is_whitespace() => {
+                if let Some(argument)=argument.take() {
+                    arguments.push(argument);
+                    }
+                },
+            (character, None) => argument.get_or_insert_with(String::new).push(character),
             }
         }
-    fn alt_down(&mut self) {
-        if !self.alt_down {
-            self.enigo.key_down(Key::Alt);
-            self.alt_down=true;
-            }
+
+    if quote.is_some() {
+        return None;
         }
-    fn meta_down(&mut self) {
-        if !self.meta_down {
-            self.enigo.key_down(Key::Meta);
-            self.meta_down=true;
-            }
+    if let Some(argument)=argument {
+        arguments.push(argument);
         }
-    fn caps_lock_down(&mut self) {
-        if !self.caps_lock_down {
-            self.enigo.key_down(Key::CapsLock);
-            self.caps_lock_down=true;
-            }
+
+    Some(arguments)
+    }
+
+/// A case-insensitive str::strip_prefix, for operation prefixes whose content has to keep its case.
+fn strip_prefix_ignore_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    match input.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&input[prefix.len()..]),
+        _ => None,
+        }
+    }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Finalizes settings parsed from json and returns the paths of the problems found, errors first.
+    fn problem_paths(json: &str) -> (Vec<String>, Vec<String>) {
+        let mut settings=serde_json::from_str::<Settings>(json).unwrap();
+        let report=settings.finalize();
+
+        (report.errors.iter().map(|error| error.path.clone()).collect(), report.warnings.iter().map(|warning| warning.path.clone()).collect())
         }
 
-    fn ctrl_up(&mut self) {
-        if self.ctrl_down {
-            self.enigo.key_up(Key::Control);
-            self.ctrl_down=false;
-            }
+    #[test]
+    fn default_slot_layout_keeps_the_original_split() {
+        let slot_layout=SlotLayout::default();
+
+        assert_eq!(slot_layout.slot(1, true, 0.5, 0.19), "1h");
+        assert_eq!(slot_layout.slot(1, true, 0.5, 0.2), "2h");
+        assert_eq!(slot_layout.slot(1, true, 0.5, 0.8), "2h");
+        assert_eq!(slot_layout.slot(1, true, 0.5, 0.81), "3h");
+        assert_eq!(slot_layout.slot(1, false, 0.2, 0.5), "2v");
+        assert_eq!(slot_layout.slot(1, false, 0.8, 0.5), "2v");
+        //A start coordinate byte of 80 from the client
+        assert_eq!(slot_layout.slot(1, false, 80f32/100f32, 0.5), "2v");
+        assert_eq!(slot_layout.slot(2, true, 0.1, 0.1), "h");
         }
-    fn shift_up(&mut self) {
-        if self.shift_down {
-            self.enigo.key_up(Key::Shift);
-            self.shift_down=false;
+
+    #[test]
+    fn operations_parse_shortcuts_and_their_errors() {
+        assert!(matches!(Operation::from_str(""), Ok(Operation::None)));
+        assert!(matches!(Operation::from_str(" Ctrl+Shift+T "), Ok(Operation::Shortcut(true, true, false, false, false, Key::Layout('t')))));
+        assert!(matches!(Operation::from_str("alt"), Ok(Operation::Shortcut(false, false, false, false, false, Key::Alt))));
+        assert!(matches!(Operation::from_str("ctrl+plus"), Ok(Operation::Shortcut(true, false, false, false, false, Key::Layout('+')))));
+
+        assert!(Operation::from_str("ctrl+").is_err());
+        assert!(Operation::from_str("ctrl+shift+capslock").is_err());
+        assert!(Operation::from_str("ctrl+a+b").is_err());
+        assert!(Operation::from_str("ctrl+nosuchkey").is_err());
+        }
+
+    #[test]
+    fn raw_keys_parse_decimal_and_hexadecimal_keycodes() {
+        assert!(matches!(Operation::from_str("raw:65"), Ok(Operation::Shortcut(false, false, false, false, false, Key::Raw(65)))));
+        assert!(matches!(Operation::from_str("ctrl+raw:0x41"), Ok(Operation::Shortcut(true, false, false, false, false, Key::Raw(0x41)))));
+
+        assert!(Operation::from_str("raw:").is_err());
+        assert!(Operation::from_str("raw:0xzz").is_err());
+        assert!(Operation::from_str("raw:70000").is_err());
+        }
+
+    #[test]
+    fn text_keeps_its_case_and_whitespace() {
+        match Operation::from_str("Text: Hello, World ") {
+            Ok(Operation::Text(text)) => assert_eq!(text, " Hello, World "),
+            operation => panic!("Expected text, got {:?}", operation),
             }
+        assert!(matches!(Operation::from_str("text:"), Ok(Operation::Text(text)) if text.is_empty()));
         }
-    fn alt_up(&mut self) {
-        if self.alt_down {
-            self.enigo.key_up(Key::Alt);
-            self.alt_down=false;
+
+    #[test]
+    fn macros_parse_steps_and_reject_invalid_ones() {
+        match Operation::from_str("macro:ctrl+c; delay:100 ;text:A\\;b;down:shift;up:shift;") {
+            Ok(Operation::Macro(steps)) => {
+                assert_eq!(steps.len(), 5);
+                assert!(matches!(steps[0], MacroStep::Operation(Operation::Shortcut(true, false, false, false, false, Key::Layout('c')))));
+                assert!(matches!(steps[1], MacroStep::Delay(delay) if delay==Duration::from_millis(100)));
+                assert!(matches!(&steps[2], MacroStep::Operation(Operation::Text(text)) if text=="A;b"));
+                assert!(matches!(steps[3], MacroStep::KeyDown(Key::Shift)));
+                assert!(matches!(steps[4], MacroStep::KeyUp(Key::Shift)));
+                },
+            operation => panic!("Expected a macro, got {:?}", operation),
             }
+
+        assert!(Operation::from_str("macro:").is_err());
+        assert!(Operation::from_str("macro: ; ;").is_err());
+        assert!(Operation::from_str("macro:delay:soon").is_err());
+        assert!(Operation::from_str("macro:delay:10000").is_ok());
+        assert!(Operation::from_str("macro:delay:99999999").is_err());
+        assert!(Operation::from_str("macro:down:nosuchkey").is_err());
+        assert!(Operation::from_str("macro:up:").is_err());
+        assert!(Operation::from_str("macro:ctrl+a+b").is_err());
+        assert!(Operation::from_str("macro:macro:a").is_err());
+        assert!(Operation::from_str("macro:repeat:10:a").is_err());
         }
-    fn meta_up(&mut self) {
-        if self.meta_down {
-            self.enigo.key_up(Key::Meta);
-            self.meta_down=false;
+
+    #[test]
+    fn run_parses_options_and_rejects_invalid_command_lines() {
+        match Operation::from_str("Run(dir=/tmp, announce):notify-send \"Hello world\" 'it''s'") {
+            Ok(Operation::Run(process)) => {
+                assert_eq!(process.program, "notify-send");
+                assert_eq!(process.arguments, vec!["Hello world", "its"]);
+                assert_eq!(process.working_directory.as_deref(), Some("/tmp"));
+                assert!(process.announce_output);
+                },
+            operation => panic!("Expected a process launch, got {:?}", operation),
+            }
+        match Operation::from_str("run:ls") {
+            Ok(Operation::Run(process)) => {
+                assert_eq!(process.program, "ls");
+                assert!(process.arguments.is_empty());
+                assert_eq!(process.working_directory, None);
+                assert!(!process.announce_output);
+                },
+            operation => panic!("Expected a process launch, got {:?}", operation),
             }
+
+        assert!(Operation::from_str("run:").is_err());
+        assert!(Operation::from_str("run:   ").is_err());
+        assert!(Operation::from_str("run:echo \"unterminated").is_err());
+        assert!(Operation::from_str("run:echo trailing\\").is_err());
+        assert!(Operation::from_str("run(announce:ls").is_err());
+        assert!(Operation::from_str("run(loudly):ls").is_err());
+        //Without a colon or options, run is just an unknown key
+        assert!(Operation::from_str("run").is_err());
         }
-    fn caps_lock_up(&mut self) {
-        if self.caps_lock_down {
-            self.enigo.key_up(Key::CapsLock);
-            self.caps_lock_down=false;
+
+    #[test]
+    fn repeats_parse_their_step_and_reject_invalid_targets() {
+        match Operation::from_str("repeat:10:ctrl+plus") {
+            Ok(Operation::Repeat(step, operation)) => {
+                assert_eq!(step, 10.0);
+                assert!(matches!(*operation, Operation::Shortcut(true, false, false, false, false, Key::Layout('+'))));
+                },
+            operation => panic!("Expected a repeat, got {:?}", operation),
             }
+
+        assert!(Operation::from_str("repeat:10").is_err());
+        assert!(Operation::from_str("repeat:0:a").is_err());
+        assert!(Operation::from_str("repeat:-5:a").is_err());
+        assert!(Operation::from_str("repeat:often:a").is_err());
+        assert!(Operation::from_str("repeat:10:nosuchkey").is_err());
+        assert!(Operation::from_str("repeat:10:repeat:5:a").is_err());
+        assert!(Operation::from_str("repeat:10:macro:a;b").is_err());
+        assert!(Operation::from_str("repeat:10:run:ls").is_err());
+
+        assert_eq!(Operation::repeat_count(10.0, 0.0), 1);
+        assert_eq!(Operation::repeat_count(10.0, 34.0), 3);
+        assert_eq!(Operation::repeat_count(0.5, 1000.0), MAXIMUM_REPEAT_COUNT);
         }
-    }
 
-struct Executor {
-    settings: Settings,
-    execution_sender: broadcast::Sender<String>,
-    key_executor: KeyExecutor,
-    }
-impl Executor {
+    #[test]
+    fn scheme_switches_parse_directions_and_ids() {
+        assert!(matches!(Operation::from_str("scheme:next"), Ok(Operation::SwitchScheme(SchemeSwitch::Next))));
+        assert!(matches!(Operation::from_str("Scheme: Prev"), Ok(Operation::SwitchScheme(SchemeSwitch::Previous))));
+        assert!(matches!(Operation::from_str("scheme:previous"), Ok(Operation::SwitchScheme(SchemeSwitch::Previous))));
+        assert!(matches!(Operation::from_str("scheme:3"), Ok(Operation::SwitchScheme(SchemeSwitch::Id(3)))));
 
-    fn new(settings: Settings, execution_sender: broadcast::Sender<String>) -> Executor {
-        Executor { settings, execution_sender, key_executor: KeyExecutor::new() }
+        assert!(Operation::from_str("scheme:").is_err());
+        assert!(Operation::from_str("scheme:last").is_err());
         }
 
-    fn process_gesture(&mut self, gesture: &Gesture) {
-        let active_scheme=&mut self.settings.schemes[0];
+    #[test]
+    fn command_lines_split_on_whitespace_with_quotes_and_escapes() {
+        assert_eq!(split_command_line("  ls   -la  "), Some(vec!["ls".to_string(), "-la".to_string()]));
+        assert_eq!(split_command_line(r#"echo "a b" 'c "d"' e\ f"#), Some(vec!["echo".to_string(), "a b".to_string(), "c \"d\"".to_string(), "e f".to_string()]));
+        assert_eq!(split_command_line(r#"printf "" x"#), Some(vec!["printf".to_string(), "".to_string(), "x".to_string()]));
+        assert_eq!(split_command_line(r#"a\\b \"c"#), Some(vec![r"a\b".to_string(), "\"c".to_string()]));
+        assert_eq!(split_command_line("   "), Some(vec![]));
+
+        assert_eq!(split_command_line(r#"echo "unterminated"#), None);
+        assert_eq!(split_command_line("echo 'unterminated"), None);
+        assert_eq!(split_command_line(r"echo trailing\"), None);
+        }
 
-        //First, check if the gesture is defined in a command binding
+    #[test]
+    fn macro_steps_split_on_unescaped_semicolons() {
+        assert_eq!(split_macro_steps("a;b"), vec!["a", "b"]);
+        assert_eq!(split_macro_steps(r"text:a\;b;c"), vec!["text:a;b", "c"]);
+        assert_eq!(split_macro_steps(r"text:a\\;b"), vec![r"text:a\", "b"]);
+        assert_eq!(split_macro_steps("a;;"), vec!["a", "", ""]);
+        //A trailing backslash escapes nothing and is dropped
+        assert_eq!(split_macro_steps(r"a\"), vec!["a"]);
+        }
 
-        for binding in &mut active_scheme.bindings.command_bindings {
-            if binding.gesture_shape_instance==gesture.shape && binding.finger_count==gesture.finger_count && binding.modifier_count==gesture.modifier_count {
-                let command=&binding.command_instance;
-                self.key_executor.execute(command.id, &command.operation, command.sticky_ctrl, command.sticky_shift, command.sticky_alt);
-                return;
-                }
-            }
+    #[test]
+    fn hosts_parse_addresses_urls_and_names() {
+        let address=|text: &str| text.parse::<SocketAddr>().unwrap();
+
+        assert_eq!(parse_host("192.168.1.5", 7321), Ok(vec![address("192.168.1.5:7321")]));
+        assert_eq!(parse_host("192.168.1.5:8000", 7321), Ok(vec![address("192.168.1.5:8000")]));
+        assert_eq!(parse_host("::1", 7321), Ok(vec![address("[::1]:7321")]));
+        assert_eq!(parse_host("[::1]:8000", 7321), Ok(vec![address("[::1]:8000")]));
+        assert_eq!(parse_host("fe80::1:2", 7321), Ok(vec![address("[fe80::1:2]:7321")]));
+
+        assert_eq!(parse_host("ws://0.0.0.0", 7321), Ok(vec![address("0.0.0.0:7321")]));
+        assert_eq!(parse_host("wss://0.0.0.0:8443", 7321), Ok(vec![address("0.0.0.0:8443")]));
+        assert_eq!(parse_host("ws://[::]:8000/", 7321), Ok(vec![address("[::]:8000")]));
+
+        let localhost=parse_host("localhost:8000", 7321).unwrap();
+        assert!(!localhost.is_empty());
+        assert!(localhost.iter().all(|address| address.ip().is_loopback() && address.port()==8000));
+        assert!(parse_host("ws://localhost", 7321).unwrap().iter().all(|address| address.port()==7321));
+
+        assert!(parse_host("", 7321).is_err());
+        assert!(parse_host("localhost:http", 7321).is_err());
+        assert!(parse_host("192.168.1.5:70000", 7321).is_err());
+        assert!(parse_host("ws://", 7321).is_err());
+        assert!(parse_host("[::1", 7321).is_err());
+        }
 
-        //If not, check slot bindings
+    #[test]
+    fn regions_parse_named_zones_and_rectangles() {
+        let third=1.0/3.0;
+
+        assert_eq!(Region::from_str(""), Ok(Region::new(0.0, 0.0, 1.0, 1.0)));
+        assert_eq!(Region::from_str(" TopLeft "), Ok(Region::new(0.0, 0.0, third, third)));
+        assert_eq!(Region::from_str("Center"), Ok(Region::new(third, third, 2.0*third, 2.0*third)));
+        assert_eq!(Region::from_str("BottomRight"), Ok(Region::new(2.0*third, 2.0*third, 1.0, 1.0)));
+        assert_eq!(Region::from_str("0, 0.5, 0.25, 1"), Ok(Region::new(0.0, 0.5, 0.25, 1.0)));
+
+        assert!(Region::from_str("topleft").is_err());
+        assert!(Region::from_str("Middle").is_err());
+        assert!(Region::from_str("0,0,1").is_err());
+        assert!(Region::from_str("0,0,1,1,1").is_err());
+        assert!(Region::from_str("0,0,a,1").is_err());
+        assert!(Region::from_str("0.5,0,0.5,1").is_err());
+        assert!(Region::from_str("0.6,0,0.4,1").is_err());
+        assert!(Region::from_str("-0.1,0,1,1").is_err());
+        assert!(Region::from_str("0,0,1,1.1").is_err());
+        }
 
-        if let Ok((slot, slot_operation))=gesture.try_get_slot_operation() {
-            for binding in &mut active_scheme.bindings.slot_bindings {
-                if binding.slot==slot && binding.finger_count==gesture.finger_count && binding.modifier_count==gesture.modifier_count {
-                    match slot_operation {
-                        SlotOperation::Forward => {
-                            if let Some(action)=binding.active_action() {
-                                self.key_executor.execute(action.id, &action.forward_operation, action.sticky_ctrl, action.sticky_shift, action.sticky_alt);
-                                }
-                            },
-                        SlotOperation::Backward => {
-                            if let Some(action)=binding.active_action() {
-                                self.key_executor.execute(action.id, &action.backward_operation, action.sticky_ctrl, action.sticky_shift, action.sticky_alt);
-                                }
-                            },
-                        SlotOperation::PreviousAction => {
-                            binding.previous_action();
-                            if let Some(active_action)=binding.active_action() {
-                                self.execution_sender.send(active_action.name.clone()).unwrap();
-                                }
-                            }
-                        SlotOperation::NextAction => {
-                            binding.next_action();
-                            if let Some(active_action)=binding.active_action() {
-                                self.execution_sender.send(active_action.name.clone()).unwrap();
-                                }
-                            }
-                        SlotOperation::DefaultAction => {
-                            binding.default_action();
-                            if let Some(active_action)=binding.active_action() {
-                                self.execution_sender.send(active_action.name.clone()).unwrap();
-                                }
-                            }
-                        }
-                    }
-                }
+    #[test]
+    fn regions_contain_their_edges() {
+        let region=Region::from_str("0.2,0.4,0.6,0.8").unwrap();
+
+        assert!(region.contains(0.2, 0.4));
+        assert!(region.contains(0.6, 0.8));
+        assert!(region.contains(0.4, 0.6));
+        assert!(!region.contains(0.19, 0.6));
+        assert!(!region.contains(0.61, 0.6));
+        assert!(!region.contains(0.4, 0.39));
+        assert!(!region.contains(0.4, 0.81));
+
+        assert!(Region::default().contains(0.0, 1.0));
+        assert!(Region::default().covers(&region));
+        assert!(!region.covers(&Region::default()));
+        }
+
+    #[test]
+    fn grid_slot_layouts_combine_rows_and_columns() {
+        let slot_layout=SlotLayout::new(vec![FingerLayout::new(1, true, vec![0.5], vec![0.25, 0.75])], false);
+
+        assert_eq!(slot_layout.slot(1, true, 0.1, 0.1), "r1c1h");
+        assert_eq!(slot_layout.slot(1, false, 0.1, 0.1), "r1c1v");
+        assert_eq!(slot_layout.slot(1, true, 0.5, 0.9), "r2c2h");
+        assert_eq!(slot_layout.slot(1, false, 0.9, 0.9), "r2c3v");
+        //The only horizontal boundary is also the last one, so it stays in the row above
+        assert_eq!(slot_layout.slot(1, true, 0.5, 0.5), "r1c2h");
+        //The first vertical boundary belongs to the column after it, the last one to the column before it
+        assert_eq!(slot_layout.slot(1, true, 0.25, 0.1), "r1c2h");
+        assert_eq!(slot_layout.slot(1, true, 0.75, 0.1), "r1c2h");
+        assert_eq!(slot_layout.slot(1, true, 0.76, 0.1), "r1c3h");
+        //Other finger counts keep a single slot per axis
+        assert_eq!(slot_layout.slot(2, false, 0.9, 0.9), "v");
+
+        assert_eq!(slot_layout.slots(1, true), vec!["r1c1h", "r1c2h", "r1c3h", "r2c1h", "r2c2h", "r2c3h"]);
+        assert_eq!(slot_layout.slots_in(1, false, &Region::from_str("0,0,0.25,0.5").unwrap()), vec!["r1c1v", "r1c2v"]);
+        }
+
+    #[test]
+    fn slot_layouts_without_grid_use_one_axis() {
+        let slot_layout=SlotLayout::new(vec![FingerLayout::new(3, false, vec![], vec![0.5])], true);
+
+        //Horizontal swipes have no boundaries and thus a single slot
+        assert_eq!(slot_layout.slot(3, true, 0.9, 0.9), "h");
+        assert_eq!(slot_layout.slot(3, false, 0.49, 0.9), "1v");
+        assert_eq!(slot_layout.slot(3, false, 0.5, 0.1), "1v");
+        assert_eq!(slot_layout.slot(3, false, 0.51, 0.1), "2v");
+
+        assert_eq!(slot_layout.slots(3, true), vec!["h"]);
+        assert_eq!(slot_layout.slots(3, false), vec!["1v", "2v"]);
+        assert_eq!(slot_layout.diagonal_slots(), vec![DOWN_RIGHT_SLOT, UP_RIGHT_SLOT]);
+        assert!(SlotLayout::default().diagonal_slots().is_empty());
+        }
+
+    #[test]
+    fn client_messages_parse_complete_payloads() {
+        match ClientMessage::from_bytes(&vec![0, 2, 1, 50, 80, 0, 0, 7]) {
+            Ok(ClientMessage::Gesture(gesture)) => {
+                assert_eq!(gesture.finger_count, 2);
+                assert_eq!(gesture.modifier_count, 1);
+                assert_eq!(gesture.start_x, 0.5);
+                assert_eq!(gesture.start_y, 0.8);
+                assert_eq!(gesture.shape, GestureShape::Swipe(vec![Direction::Left, Direction::DownRight]));
+                },
+            message => panic!("Expected a gesture, got {:?}", message),
+            }
+        match ClientMessage::from_bytes(&vec![0, 2, 0, 50, 50, 10, 1, 44]) {
+            Ok(ClientMessage::Gesture(gesture)) => {
+                assert_eq!(gesture.shape, GestureShape::PinchOut);
+                assert_eq!(gesture.magnitude, 300.0);
+                },
+            message => panic!("Expected a gesture, got {:?}", message),
             }
+        assert!(matches!(ClientMessage::from_bytes(&vec![0, 1, 0, 50, 50, 1]), Ok(ClientMessage::Gesture(gesture)) if gesture.shape==GestureShape::Tap));
+        assert!(matches!(ClientMessage::from_bytes(&vec![4, 1, 0, 0, 0, 3]), Ok(ClientMessage::Hello(1, 3))));
+        assert!(matches!(ClientMessage::from_bytes(&vec![5, 0xFF, 0xFE, 0, 5]), Ok(ClientMessage::PointerMotion(-2, 5))));
         }
 
-    }
+    #[test]
+    fn truncated_client_messages_are_errors() {
+        assert!(ClientMessage::from_bytes(&vec![]).is_err());
 
-#[tokio::main]
-async fn main() {
-    let (communication_sender, communication_receiver)=mpsc::channel::<ClientMessage>(10);
-    let (execution_sender, _)=broadcast::channel::<String>(10);
+        //Gestures without their header
+        for length in 1..6 {
+            assert!(ClientMessage::from_bytes(&vec![0, 1, 0, 50, 50, 0][..length].to_vec()).is_err());
+            }
+        //Swipes and hold-then-swipes without directions, or with unknown ones
+        assert!(ClientMessage::from_bytes(&vec![0, 1, 0, 50, 50, 0]).is_err());
+        assert!(ClientMessage::from_bytes(&vec![0, 1, 0, 50, 50, 6]).is_err());
+        assert!(ClientMessage::from_bytes(&vec![0, 1, 0, 50, 50, 0, 8]).is_err());
+        //Pinches and rotations without a complete magnitude
+        for shape in 9..=12 {
+            assert!(ClientMessage::from_bytes(&vec![0, 2, 0, 50, 50, shape]).is_err());
+            assert!(ClientMessage::from_bytes(&vec![0, 2, 0, 50, 50, shape, 1]).is_err());
+            }
+        assert!(ClientMessage::from_bytes(&vec![0, 1, 0, 50, 50, 13]).is_err());
 
-    tokio::spawn(execution_thread(communication_receiver, execution_sender.clone()));
+        assert!(ClientMessage::from_bytes(&vec![1]).is_err());
+        for length in 1..6 {
+            assert!(ClientMessage::from_bytes(&vec![4, 1, 0, 0, 0, 3][..length].to_vec()).is_err());
+            }
+        //Trackpad pointer motion without both deltas
+        for length in 1..5 {
+            assert!(ClientMessage::from_bytes(&vec![5, 0, 1, 0, 1][..length].to_vec()).is_err());
+            }
+        assert!(ClientMessage::from_bytes(&vec![2, 0xFF]).is_err());
+        assert!(ClientMessage::from_bytes(&vec![6]).is_err());
+        }
 
-    let server=TcpListener::bind(&get_host()).await.unwrap();
+    #[test]
+    fn conflicting_bindings_are_warned_about() {
+        let json=r#"{
+            "actions": [{"id": 1, "forwardShortcut": "a", "backwardShortcut": "b"}],
+            "commands": [{"id": 10, "shortcut": "Return"}],
+            "rings": [{"id": 20, "actions": [1]}],
+            "schemes": [{"id": 30, "bindings": {
+                "slotBindings": [
+                    {"id": 40, "slot": "1h", "ring": 20, "defaultAction": 1},
+                    {"id": 41, "slot": "2h", "ring": 20, "defaultAction": 1},
+                    {"id": 42, "slot": "3h", "ring": 20, "defaultAction": 1},
+                    {"id": 43, "slot": "2v", "ring": 20, "defaultAction": 1},
+                    {"id": 44, "slot": "2v", "ring": 20, "defaultAction": 1}
+                    ],
+                "commandBindings": [
+                    {"id": 50, "gestureShape": "Tap", "command": 10},
+                    {"id": 51, "gestureShape": "Tap", "command": 10, "region": "TopLeft"},
+                    {"id": 52, "gestureShape": "Swipe", "swipeDirections": ["Right"], "command": 10, "region": "0,0,1,0.5"},
+                    {"id": 53, "gestureShape": "Swipe", "swipeDirections": ["DownRight"], "command": 10},
+                    {"id": 54, "gestureShape": "Swipe", "swipeDirections": ["Up", "Right", "Down", "Left"], "command": 10},
+                    {"id": 55, "gestureShape": "Tap", "command": 10, "fingerCount": 2, "region": "TopLeft"}
+                    ]
+                }}]
+            }"#;
+
+        let (errors, warnings)=problem_paths(json);
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(warnings, vec![
+            //The tap in the top left corner is covered by the earlier full screen tap
+            "schemes[0].bindings.commandBindings[1]",
+            //The swipe limited to the upper half intercepts the horizontal slots starting there, but not 3h
+            "schemes[0].bindings.slotBindings[0]",
+            "schemes[0].bindings.slotBindings[1]",
+            //The diagonal swipe with diagonals off and the four-direction swipe are no slot gestures, so they shadow nothing
+            //Two bindings of the same slot both execute
+            "schemes[0].bindings.slotBindings[4]",
+            ]);
+        }
 
-    println!("Launched server on {:?}", local_ip_address::local_ip().unwrap());
+    #[test]
+    fn diagonal_command_bindings_shadow_diagonal_slots() {
+        let json=r#"{
+            "actions": [{"id": 1, "forwardShortcut": "a", "backwardShortcut": "b"}],
+            "commands": [{"id": 10, "shortcut": "Return"}],
+            "rings": [{"id": 20, "actions": [1]}],
+            "schemes": [{"id": 30, "bindings": {
+                "slotBindings": [
+                    {"id": 40, "slot": "dr", "ring": 20, "defaultAction": 1},
+                    {"id": 41, "slot": "ur", "ring": 20, "defaultAction": 1}
+                    ],
+                "commandBindings": [
+                    {"id": 50, "gestureShape": "Swipe", "swipeDirections": ["UpLeft"], "command": 10, "region": "BottomRight"}
+                    ]
+                }}],
+            "slotLayout": {"layouts": [{"fingerCount": 1, "horizontalBoundaries": [0.2, 0.8], "verticalBoundaries": [0.2, 0.8]}], "diagonals": true}
+            }"#;
+
+        let (errors, warnings)=problem_paths(json);
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(warnings, vec!["schemes[0].bindings.slotBindings[0]"]);
+        }
 
-    while let Ok((stream, _))=server.accept().await {
-        println!("Incoming stream");
-        let execution_receiver=execution_sender.subscribe();
-        tokio::spawn(communication_thread(stream, communication_sender.clone(), execution_receiver));
+    #[test]
+    fn every_settings_error_is_reported() {
+        let json=r#"{
+            "actions": [
+                {"id": 1, "forwardShortcut": "ctrl+nosuchkey", "backwardShortcut": "a"},
+                {"id": 1, "forwardShortcut": "scheme:99", "backwardShortcut": "a"}
+                ],
+            "commands": [
+                {"id": 10, "shortcut": "macro:a;scheme:98"},
+                {"id": 11, "shortcut": "repeat:10:scheme:97"},
+                {"id": 12, "shortcut": "scheme:30"}
+                ],
+            "rings": [{"id": 20, "actions": [1, 2]}],
+            "schemes": [
+                {"id": 30, "bindings": {
+                    "slotBindings": [
+                        {"id": 40, "slot": "9h", "ring": 20, "defaultAction": 1},
+                        {"id": 41, "slot": "2h", "ring": 21, "defaultAction": 1},
+                        {"id": 42, "slot": "2v", "ring": 20, "defaultAction": 3}
+                        ],
+                    "commandBindings": [
+                        {"id": 50, "gestureShape": "Wave", "command": 10},
+                        {"id": 50, "gestureShape": "Swipe", "swipeDirections": ["Sideways"], "command": 13, "region": "Middle"}
+                        ]
+                    }},
+                {"id": 30}
+                ],
+            "slotLayout": {"layouts": [{"fingerCount": 1, "horizontalBoundaries": [0.8, 0.2], "verticalBoundaries": [0.2, 0.8]}]}
+            }"#;
+
+        let (errors, warnings)=problem_paths(json);
+
+        assert_eq!(errors, vec![
+            "actions[1].id",
+            "schemes[1].id",
+            "slotLayout.layouts[0].horizontalBoundaries[1]",
+            "actions[0].forwardShortcut",
+            "rings[0].actions[1]",
+            "schemes[0].bindings.commandBindings[1].id",
+            "schemes[0].bindings.slotBindings[0].slot",
+            "schemes[0].bindings.slotBindings[1].ring",
+            "schemes[0].bindings.slotBindings[2].defaultAction",
+            "schemes[0].bindings.commandBindings[0].gestureShape",
+            "schemes[0].bindings.commandBindings[1].swipeDirections[0]",
+            "schemes[0].bindings.commandBindings[1].region",
+            "schemes[0].bindings.commandBindings[1].command",
+            //Dangling scheme references are found in plain operations as well as within macros and repeats, while scheme 30 exists
+            "actions[1].forwardShortcut",
+            "commands[0].shortcut",
+            "commands[1].shortcut",
+            ]);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let message=match Settings::from_json(json) {
+            Ok(_) => panic!("Settings with errors were accepted"),
+            Err(message) => message,
+            };
+        assert!(message.starts_with("Found 16 errors and 0 warnings in the settings:"), "{}", message);
         }
-    }
 
-async fn communication_thread(stream: TcpStream, communication_sender: mpsc::Sender<ClientMessage>, mut execution_receiver: broadcast::Receiver<String>) {
-    let ws_stream=tokio_tungstenite::accept_async(stream).await.unwrap();
-    let (mut ws_sender, mut ws_receiver)=ws_stream.split();
+    fn authentication_response(password: &str, challenge: &[u8]) -> Vec<u8> {
+        let mut mac=HmacSha256::new_from_slice(password.as_bytes()).unwrap();
+        mac.update(challenge);
 
-    println!("New connection established");
+        mac.finalize().into_bytes().to_vec()
+        }
 
-    let mut authenticated=false;
+    #[test]
+    fn authentication_responses_are_checked_against_the_password() {
+        let challenge=generate_authentication_challenge();
+        let response=authentication_response("secret", &challenge);
 
-    loop {
-        tokio::select! {
-            msg = ws_receiver.next() => {
-                if let Some(Ok(msg))=msg {
-                    match msg {
-                        Message::Binary(data) => {
-                            if !authenticated { continue; }
+        assert_eq!(challenge.len(), AUTHENTICATION_CHALLENGE_LENGTH);
+        assert!(verify_authentication_response("secret", &challenge, &response));
 
-                            if let Ok(client_message)=ClientMessage::from_bytes(&data) {
-                                communication_sender.send(client_message).await.unwrap();
-                                }
-                            },
-                        Message::Text(text) => {
-                            if text=="random_password" {
-                                authenticated=true;
-                                println!("Authenticated!");
-                                continue;
-                                }
-                            },
-                        _ => {},
-                        }
-                    }
-                else {
-                    break;
-                    }
-                }
-            msg = execution_receiver.recv() => {
-                if let Ok(msg)=msg {
-                    ws_sender.send(Message::Text(msg)).await.unwrap();
-                    }
-                }
-            }
+        assert!(!verify_authentication_response("Secret", &challenge, &response));
+        assert!(!verify_authentication_response("secret", &generate_authentication_challenge(), &response));
+        assert!(!verify_authentication_response("secret", &challenge, &response[..response.len()-1]));
+        assert!(!verify_authentication_response("secret", &challenge, &[]));
+        assert!(!verify_authentication_response("secret", &challenge, b"secret"));
         }
 
-    println!("A connection closed");
-    }
-async fn execution_thread(mut communication_receiver: mpsc::Receiver<ClientMessage>, execution_sender: broadcast::Sender<String>) {
-    let settings=Settings::from_json(&fs::read_to_string("settings.json").unwrap()).unwrap();
+    #[test]
+    fn failing_addresses_are_locked_out_for_longer_and_longer() {
+        let authenticator=Authenticator::new("secret".to_string());
+        let address: IpAddr="192.168.1.5".parse().unwrap();
+        let other_address: IpAddr="192.168.1.6".parse().unwrap();
+        let challenge=generate_authentication_challenge();
 
-    let mut executor=Executor::new(settings, execution_sender);
+        for _ in 1..AUTHENTICATION_ATTEMPTS {
+            assert!(!authenticator.verify(address, &challenge, b"guess"));
+            assert_eq!(authenticator.lockout(address), None);
+            }
 
-    while let Some(client_message)=communication_receiver.recv().await {
-        match client_message {
-            ClientMessage::Gesture(gesture) => executor.process_gesture(&gesture),
+        assert!(!authenticator.verify(address, &challenge, b"guess"));
+        let lockout=authenticator.lockout(address).unwrap();
+        assert!(lockout<=AUTHENTICATION_LOCKOUT && lockout>AUTHENTICATION_LOCKOUT-Duration::from_secs(1));
+        assert_eq!(authenticator.lockout(other_address), None);
+
+        //Every further failure doubles the lockout, up to the maximum
+        assert!(!authenticator.verify(address, &challenge, b"guess"));
+        assert!(authenticator.lockout(address).unwrap()>AUTHENTICATION_LOCKOUT);
+        for _ in 0..20 {
+            authenticator.verify(address, &challenge, b"guess");
             }
+        let lockout=authenticator.lockout(address).unwrap();
+        assert!(lockout<=AUTHENTICATION_MAXIMUM_LOCKOUT && lockout>AUTHENTICATION_MAXIMUM_LOCKOUT-Duration::from_secs(1));
+
+        //The lockout ends with time
+        assert_eq!(authenticator.lockout_at(address, Instant::now()+AUTHENTICATION_MAXIMUM_LOCKOUT), None);
+
+        //A success clears the failures, the next failure starts from scratch
+        assert!(authenticator.verify(address, &challenge, &authentication_response("secret", &challenge)));
+        assert_eq!(authenticator.lockout(address), None);
+        assert!(!authenticator.verify(address, &challenge, b"guess"));
+        assert_eq!(authenticator.lockout(address), None);
         }
-    }
 
-fn get_host() -> String {
-    if let Ok(host)=std::env::var("RBRIDGE_HOST") {
-        if let Ok(url)=Url::parse(&host) {
-            if let Some(port)=url.port() {
-                return format!("127.0.0.1:{}", port);
-                }
+    #[test]
+    fn old_authentication_failures_are_forgotten() {
+        let authenticator=Authenticator::new("secret".to_string());
+        let address: IpAddr="::1".parse().unwrap();
+        let challenge=generate_authentication_challenge();
+
+        for _ in 0..AUTHENTICATION_ATTEMPTS {
+            authenticator.verify(address, &challenge, b"guess");
             }
-        }
 
-    "0.0.0.0:7321".to_string()
+        let mut failures=authenticator.failures.lock().unwrap();
+
+        Authenticator::forget_old_failures(&mut failures, Instant::now()+AUTHENTICATION_FAILURE_MEMORY-Duration::from_secs(60));
+        assert_eq!(failures.get(&address).map(|record| record.count), Some(AUTHENTICATION_ATTEMPTS));
+
+        Authenticator::forget_old_failures(&mut failures, Instant::now()+AUTHENTICATION_FAILURE_MEMORY);
+        assert!(failures.is_empty());
+        }
     }